@@ -0,0 +1,182 @@
+//! Lazily initialized backends, commands only initialize the backends
+//! they make use of
+
+use crate::{search::SearchClient, storage::StorageClient};
+use aws_config::SdkConfig;
+use docbox_management::{
+    config::{AdminDatabaseSetupUserConfig, ServerConfigData},
+    core::{
+        aws::SqsClient,
+        events::{EventPublisherFactory, sqs::SqsEventPublisherFactory},
+        search::SearchIndexFactory,
+        secrets::SecretManager,
+        storage::StorageLayerFactory,
+    },
+    database::{DatabasePoolCache, DatabasePoolCacheConfig, ServerDatabaseProvider},
+};
+use eyre::{Context, ContextCompat};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Backends for the docbox server, each backend is created on first use
+pub struct Backends<'a> {
+    aws_config: &'a SdkConfig,
+    config: &'a ServerConfigData,
+
+    secrets: OnceCell<SecretManager>,
+    db_cache: OnceCell<Arc<DatabasePoolCache>>,
+    db_provider: OnceCell<ServerDatabaseProvider>,
+    search: OnceCell<SearchIndexFactory>,
+    search_client: OnceCell<SearchClient>,
+    storage: OnceCell<StorageLayerFactory>,
+    storage_client: OnceCell<StorageClient>,
+    sqs_client: OnceCell<SqsClient>,
+    events: OnceCell<EventPublisherFactory>,
+}
+
+impl<'a> Backends<'a> {
+    pub fn new(aws_config: &'a SdkConfig, config: &'a ServerConfigData) -> Self {
+        Self {
+            aws_config,
+            config,
+            secrets: OnceCell::new(),
+            db_cache: OnceCell::new(),
+            db_provider: OnceCell::new(),
+            search: OnceCell::new(),
+            search_client: OnceCell::new(),
+            storage: OnceCell::new(),
+            storage_client: OnceCell::new(),
+            sqs_client: OnceCell::new(),
+            events: OnceCell::new(),
+        }
+    }
+
+    /// Secrets manager access
+    pub async fn secrets(&self) -> &SecretManager {
+        self.secrets
+            .get_or_init(|| async {
+                SecretManager::from_config(self.aws_config, self.config.secrets.clone())
+            })
+            .await
+    }
+
+    /// Tenant database pool cache
+    pub async fn db_cache(&self) -> &Arc<DatabasePoolCache> {
+        let secrets = self.secrets().await;
+
+        self.db_cache
+            .get_or_init(|| async {
+                Arc::new(DatabasePoolCache::from_config(
+                    self.aws_config.clone(),
+                    DatabasePoolCacheConfig {
+                        host: self.config.database.host.clone(),
+                        port: self.config.database.port,
+                        root_secret_name: self.config.database.root_secret_name.clone(),
+                        root_iam: self.config.database.root_iam,
+                        ..Default::default()
+                    },
+                    secrets.clone(),
+                ))
+            })
+            .await
+    }
+
+    /// Database provider using the setup user for management database access
+    pub async fn db_provider(&self) -> eyre::Result<&ServerDatabaseProvider> {
+        self.db_provider
+            .get_or_try_init(|| async {
+                let database = &self.config.database;
+
+                let setup_user: AdminDatabaseSetupUserConfig = match (
+                    database.setup_user.as_ref(),
+                    database.setup_user_secret_name.as_deref(),
+                ) {
+                    (Some(setup_user), _) => setup_user.clone(),
+                    (_, Some(setup_user_secret_name)) => self
+                        .secrets()
+                        .await
+                        .parsed_secret(setup_user_secret_name)
+                        .await
+                        .context("failed to load database setup user secret")?
+                        .context("database setup user secret not found")?,
+                    (None, None) => eyre::bail!(
+                        "must provided either setup_user or setup_user_secret_name in database config"
+                    ),
+                };
+
+                Ok(ServerDatabaseProvider {
+                    config: database.clone(),
+                    username: setup_user.username,
+                    password: setup_user.password,
+                })
+            })
+            .await
+    }
+
+    /// Search index access
+    pub async fn search(&self) -> eyre::Result<&SearchIndexFactory> {
+        let secrets = self.secrets().await;
+        let db_cache = self.db_cache().await;
+
+        self.search
+            .get_or_try_init(|| async {
+                SearchIndexFactory::from_config(
+                    self.aws_config,
+                    secrets.clone(),
+                    db_cache.clone(),
+                    self.config.search.clone(),
+                )
+                .context("failed to create search index factory")
+            })
+            .await
+    }
+
+    /// Direct search access
+    pub async fn search_client(&self) -> eyre::Result<&SearchClient> {
+        let secrets = self.secrets().await;
+
+        self.search_client
+            .get_or_try_init(|| async {
+                SearchClient::from_config(self.aws_config, secrets, &self.config.search)
+                    .await
+                    .context("failed to create search client")
+            })
+            .await
+    }
+
+    /// Storage access
+    pub async fn storage(&self) -> &StorageLayerFactory {
+        self.storage
+            .get_or_init(|| async {
+                StorageLayerFactory::from_config(self.aws_config, self.config.storage.clone())
+            })
+            .await
+    }
+
+    /// Direct storage access
+    pub async fn storage_client(&self) -> &StorageClient {
+        self.storage_client
+            .get_or_init(|| async {
+                StorageClient::from_config(self.aws_config, &self.config.storage)
+            })
+            .await
+    }
+
+    /// Direct event queue access
+    pub async fn sqs_client(&self) -> &SqsClient {
+        self.sqs_client
+            .get_or_init(|| async { SqsClient::new(self.aws_config) })
+            .await
+    }
+
+    /// Event publishing access
+    pub async fn events(&self) -> &EventPublisherFactory {
+        let sqs_client = self.sqs_client().await;
+
+        self.events
+            .get_or_init(|| async {
+                EventPublisherFactory::new(SqsEventPublisherFactory::new(sqs_client.clone()))
+            })
+            .await
+    }
+}
@@ -0,0 +1,148 @@
+//! Column selection and sorting for tenant tables
+
+use clap::ValueEnum;
+use comfy_table::Cell;
+use docbox_management::database::models::tenant::Tenant;
+use serde_json::{Map, Value};
+
+/// Tenant field that can be shown as a column
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+#[value(rename_all = "snake_case")]
+pub enum TenantColumn {
+    Id,
+    Name,
+    Env,
+    DbName,
+    DbSecretName,
+    DbIamUserName,
+    S3Name,
+    OsIndexName,
+    EventQueueUrl,
+}
+
+impl TenantColumn {
+    /// Header for the column in human readable tables
+    pub fn header(&self) -> &'static str {
+        match self {
+            TenantColumn::Id => "ID",
+            TenantColumn::Name => "Name",
+            TenantColumn::Env => "Env",
+            TenantColumn::DbName => "DB Name",
+            TenantColumn::DbSecretName => "DB Secret Name",
+            TenantColumn::DbIamUserName => "DB IAM User Name",
+            TenantColumn::S3Name => "Storage Bucket Name",
+            TenantColumn::OsIndexName => "Search Index Name",
+            TenantColumn::EventQueueUrl => "Event Queue URL",
+        }
+    }
+
+    /// Key for the column in CSV output, matches the tenant JSON field
+    pub fn key(&self) -> &'static str {
+        match self {
+            TenantColumn::Id => "id",
+            TenantColumn::Name => "name",
+            TenantColumn::Env => "env",
+            TenantColumn::DbName => "db_name",
+            TenantColumn::DbSecretName => "db_secret_name",
+            TenantColumn::DbIamUserName => "db_iam_user_name",
+            TenantColumn::S3Name => "s3_name",
+            TenantColumn::OsIndexName => "os_index_name",
+            TenantColumn::EventQueueUrl => "event_queue_url",
+        }
+    }
+
+    /// Whether the column is part of the tenant identity which is known
+    /// without loading the rest of the tenant
+    pub fn is_identity(&self) -> bool {
+        matches!(
+            self,
+            TenantColumn::Id | TenantColumn::Name | TenantColumn::Env
+        )
+    }
+
+    /// Value of the column for a tenant, missing values are empty
+    pub fn value(&self, tenant: &Tenant) -> String {
+        match self {
+            TenantColumn::Id => tenant.id.to_string(),
+            TenantColumn::Name => tenant.name.clone(),
+            TenantColumn::Env => tenant.env.clone(),
+            TenantColumn::DbName => tenant.db_name.clone(),
+            TenantColumn::DbSecretName => tenant.db_secret_name.clone().unwrap_or_default(),
+            TenantColumn::DbIamUserName => tenant.db_iam_user_name.clone().unwrap_or_default(),
+            TenantColumn::S3Name => tenant.s3_name.clone(),
+            TenantColumn::OsIndexName => tenant.os_index_name.clone(),
+            TenantColumn::EventQueueUrl => tenant.event_queue_url.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Item that can be shown as a row of a tenant table
+pub trait TenantRow {
+    /// Value of the `column`, [None] when the value is not known
+    fn column_value(&self, column: TenantColumn) -> Option<String>;
+}
+
+impl TenantRow for Tenant {
+    fn column_value(&self, column: TenantColumn) -> Option<String> {
+        Some(column.value(self))
+    }
+}
+
+/// Column selection and sorting arguments for commands listing tenants
+#[derive(clap::Args, Clone)]
+pub struct TableArgs {
+    /// Columns to include in human readable and CSV output
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "id,name,env")]
+    pub columns: Vec<TenantColumn>,
+
+    /// Column to sort the tenants by
+    #[arg(long, value_enum)]
+    pub sort_by: Option<TenantColumn>,
+}
+
+impl TableArgs {
+    /// Headers for the selected columns
+    pub fn headers(&self) -> Vec<&'static str> {
+        self.columns.iter().map(TenantColumn::header).collect()
+    }
+
+    /// Whether the selected columns need more than the tenant identity
+    pub fn needs_details(&self) -> bool {
+        self.columns
+            .iter()
+            .chain(&self.sort_by)
+            .any(|column| !column.is_identity())
+    }
+
+    /// Cells for the selected columns of a tenant
+    pub fn cells(&self, tenant: &impl TenantRow) -> Vec<Cell> {
+        self.columns
+            .iter()
+            .map(|column| Cell::new(tenant.column_value(*column).unwrap_or_default()))
+            .collect()
+    }
+
+    /// CSV row for the selected columns of a tenant, unknown values are null
+    pub fn row(&self, tenant: &impl TenantRow) -> Map<String, Value> {
+        self.columns
+            .iter()
+            .map(|column| {
+                (
+                    column.key().to_string(),
+                    tenant
+                        .column_value(*column)
+                        .map(Value::String)
+                        .unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Sort items by the tenant `sort_by` column, keeps the original order
+    /// when no sort column is selected
+    pub fn sort<T, R: TenantRow>(&self, items: &mut [T], tenant: impl Fn(&T) -> &R) {
+        if let Some(column) = self.sort_by {
+            items.sort_by_cached_key(|item| tenant(item).column_value(column).unwrap_or_default());
+        }
+    }
+}
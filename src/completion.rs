@@ -0,0 +1,207 @@
+//! Dynamic shell completion of environments and tenant IDs
+//!
+//! Completions are provided from a local cache of tenants that is updated
+//! whenever the full list of tenants is loaded (i.e `get-tenants`), each
+//! context has its own cache
+
+use crate::context::ContextsFile;
+use clap::CommandFactory;
+use clap_complete::CompletionCandidate;
+use docbox_management::database::models::tenant::{Tenant, TenantId};
+use eyre::{Context, ContextCompat};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Tenant within the completion cache
+#[derive(Serialize, Deserialize)]
+struct CachedTenant {
+    env: String,
+    id: TenantId,
+    name: String,
+}
+
+/// Path to the completion cache file for the `context`, configs that are not
+/// loaded from a context share a single cache
+fn cache_path(context: Option<&str>) -> eyre::Result<PathBuf> {
+    let cache_dir = dirs::cache_dir().context("failed to determine cache directory")?;
+    let file_name = match context {
+        // Context names are user provided, encode them for use as a file name
+        Some(context) => format!(
+            "tenants-{}.json",
+            utf8_percent_encode(context, NON_ALPHANUMERIC)
+        ),
+        None => "tenants.json".to_string(),
+    };
+
+    Ok(cache_dir.join("docbox-cli").join(file_name))
+}
+
+/// Context of the command line being completed, matches the context that
+/// the command would load its config from
+fn completion_context() -> Option<String> {
+    args_context(std::env::args().skip(1))?.or_else(ContextsFile::current_name)
+}
+
+/// Context selected by the command line `args`, [None] when an explicit config
+/// is provided otherwise the `--context` value if there is one
+///
+/// Only the arguments before the subcommand are checked, the config options
+/// are not global and their short names are reused by subcommands
+fn args_context(mut args: impl Iterator<Item = String>) -> Option<Option<String>> {
+    let command = crate::Args::command();
+    let takes_value = |argument: Option<&clap::Arg>| {
+        argument.is_some_and(|argument| argument.get_action().takes_values())
+    };
+
+    let mut context = None;
+
+    while let Some(arg) = args.next() {
+        if command.find_subcommand(&arg).is_some() {
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+
+            match name {
+                // Explicit configs do not use a context
+                "config" | "aws-config-secret" => return None,
+                "context" => context = value.or_else(|| args.next()),
+                name => {
+                    let argument = command
+                        .get_arguments()
+                        .find(|argument| argument.get_long() == Some(name));
+                    if value.is_none() && takes_value(argument) {
+                        args.next();
+                    }
+                }
+            }
+        } else if let Some(short) = arg.strip_prefix('-').and_then(|arg| arg.chars().next()) {
+            if matches!(short, 'c' | 'a') {
+                return None;
+            }
+
+            let argument = command
+                .get_arguments()
+                .find(|argument| argument.get_short() == Some(short));
+            if arg.len() == 2 && takes_value(argument) {
+                args.next();
+            }
+        }
+    }
+
+    Some(context)
+}
+
+/// Replace the cached tenants used for completions within the `context`
+pub async fn update_tenant_cache(context: Option<&str>, tenants: &[Tenant]) -> eyre::Result<()> {
+    let path = cache_path(context)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let tenants: Vec<CachedTenant> = tenants
+        .iter()
+        .map(|tenant| CachedTenant {
+            env: tenant.env.clone(),
+            id: tenant.id,
+            name: tenant.name.clone(),
+        })
+        .collect();
+
+    tokio::fs::write(&path, serde_json::to_string(&tenants)?)
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Load the cached tenants, completions must not fail so a missing or
+/// invalid cache provides no tenants
+fn load_tenant_cache() -> Vec<CachedTenant> {
+    cache_path(completion_context().as_deref())
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Complete the environments of the cached tenants
+pub fn complete_env() -> Vec<CompletionCandidate> {
+    let mut envs: Vec<String> = load_tenant_cache()
+        .into_iter()
+        .map(|tenant| tenant.env)
+        .collect();
+
+    envs.sort();
+    envs.dedup();
+
+    envs.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Complete the IDs of the cached tenants, the tenant name and environment
+/// are provided as help
+pub fn complete_tenant_id() -> Vec<CompletionCandidate> {
+    load_tenant_cache()
+        .into_iter()
+        .map(|tenant| {
+            CompletionCandidate::new(tenant.id.to_string())
+                .help(Some(format!("{} ({})", tenant.name, tenant.env).into()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::args_context;
+
+    fn context(line: &str) -> Option<Option<String>> {
+        args_context(shlex::split(line).unwrap().into_iter())
+    }
+
+    /// Tests that the context is taken from the arguments before the subcommand
+    #[test]
+    fn test_args_context() {
+        assert_eq!(context("get-tenants"), Some(None));
+        assert_eq!(
+            context("--context prod get-tenants"),
+            Some(Some("prod".to_string()))
+        );
+        assert_eq!(
+            context("-f json --context=prod get-tenants"),
+            Some(Some("prod".to_string()))
+        );
+    }
+
+    /// Tests that an explicit config before the subcommand disables the context
+    #[test]
+    fn test_args_context_explicit_config() {
+        assert_eq!(context("-c config.json get-tenants"), None);
+        assert_eq!(context("--config=config.json get-tenants"), None);
+        assert_eq!(context("-a secret get-tenants"), None);
+        assert_eq!(context("--aws-config-secret secret get-tenants"), None);
+    }
+
+    /// Tests that subcommand arguments sharing a config short name are ignored
+    #[test]
+    fn test_args_context_subcommand_args() {
+        assert_eq!(
+            context("--context prod delete-tenant -c true -t"),
+            Some(Some("prod".to_string()))
+        );
+        assert_eq!(context("delete-tenant -c true -a"), Some(None));
+    }
+
+    /// Tests that completion requests ("<bin> -- <bin> <args>") are handled
+    #[test]
+    fn test_args_context_completion_request() {
+        assert_eq!(
+            context("-- docbox-cli --context prod get-tenant -e"),
+            Some(Some("prod".to_string()))
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! Loading, validating and creating the cli configuration
+
+use crate::{
+    context::ConfigSource, error::ErrorCode, search::SearchClient, storage::StorageClient,
+};
+use aws_config::SdkConfig;
+use dialoguer::{Confirm, Input, Select};
+use docbox_management::{
+    config::{AdminDatabaseSetupUserConfig, ServerConfigData, load_server_config_data_secret},
+    core::secrets::SecretManager,
+    database::{PgConnectOptions, PgPool, close_pool_on_drop, sqlx},
+};
+use eyre::{Context, ContextCompat};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Replacement value for redacted secrets
+const REDACTED: &str = "<redacted>";
+
+/// Keys within the config that contain secret values
+const SECRET_KEYS: &[&str] = &["password", "api_key", "access_key_secret"];
+
+/// Prefix for environment variables providing config values
+const ENV_PREFIX: &str = "DOCBOX_";
+
+/// Separator between the path segments of config environment variables
+const ENV_SEPARATOR: &str = "__";
+
+/// Top level sections of the config that can be set from environment variables
+const ENV_SECTIONS: &[&str] = &["api", "database", "secrets", "search", "storage"];
+
+/// Config fields that are not strings
+const ENV_NON_STRING_KEYS: &[&str] = &["port", "root_iam"];
+
+/// Load the config data, layering the sources from lowest to highest priority:
+///
+/// - AWS secret
+/// - Config files in the order they were provided
+/// - `DOCBOX_*` environment variables
+pub async fn load_config(
+    aws_config: &SdkConfig,
+    source: ConfigSource,
+) -> eyre::Result<ServerConfigData> {
+    let mut config = Value::Object(Default::default());
+
+    if let Some(config_secret_name) = source.aws_config_secret {
+        let secret_config = load_server_config_data_secret(aws_config, &config_secret_name)
+            .await
+            .context("failed to load config secret")?;
+        merge_config_values(&mut config, serde_json::to_value(secret_config)?);
+    }
+
+    for config_path in &source.config {
+        merge_config_values(&mut config, read_config_file(config_path).await?);
+    }
+
+    merge_config_values(&mut config, env_config_value(std::env::vars())?);
+
+    if config.as_object().is_some_and(|config| config.is_empty()) {
+        return Err(eyre::Report::new(ErrorCode::ConfigMissing).wrap_err(
+            "must provided either --config, --aws-config-secret, --context or DOCBOX_* environment variables check --help for more details",
+        ));
+    }
+
+    serde_json::from_value(config).context("failed to parse config")
+}
+
+/// Format of a config file
+#[derive(Clone, Copy)]
+enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Toml => "toml",
+        }
+    }
+
+    fn parse(&self, raw: &str) -> eyre::Result<Value> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::from_str(raw)?,
+            ConfigFormat::Yaml => serde_norway::from_str(raw)?,
+            ConfigFormat::Toml => toml::from_str(raw)?,
+        })
+    }
+}
+
+/// Read a config file, a path of "-" reads the config from stdin
+///
+/// Files without a known extension are tried as JSON, TOML then YAML, the
+/// errors from every format are reported when none of them can be parsed
+async fn read_config_file(path: &Path) -> eyre::Result<Value> {
+    let raw = if path == Path::new("-") {
+        let mut raw = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut raw)
+            .await
+            .context("failed to read config from stdin")?;
+        raw
+    } else {
+        tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read config {}", path.display()))?
+    };
+
+    match ConfigFormat::from_path(path) {
+        Some(format) => format
+            .parse(&raw)
+            .with_context(|| format!("failed to parse config {}", path.display())),
+        None => parse_unknown_config_format(&raw)
+            .with_context(|| format!("failed to parse config {}", path.display())),
+    }
+}
+
+/// Parse a config of unknown format, only values containing a table of config
+/// values are accepted as YAML parses almost any text as a single value
+fn parse_unknown_config_format(raw: &str) -> eyre::Result<Value> {
+    let mut errors = Vec::new();
+
+    for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+        match format.parse(raw) {
+            Ok(value) if value.is_object() => return Ok(value),
+            Ok(_) => errors.push(format!(
+                "{}: expected a table of config values",
+                format.name()
+            )),
+            Err(error) => errors.push(format!("{}: {error}", format.name())),
+        }
+    }
+
+    Err(
+        eyre::Report::new(ErrorCode::ConfigParseFailed).wrap_err(format!(
+            "unknown config format, use a .json, .toml, .yaml or .yml extension ({})",
+            errors.join(", ")
+        )),
+    )
+}
+
+/// Build a config value from the `DOCBOX_*` environment variables, nested
+/// fields are separated by a double underscore (`DOCBOX_DATABASE__HOST`)
+fn env_config_value(vars: impl IntoIterator<Item = (String, String)>) -> eyre::Result<Value> {
+    let mut config = Value::Object(Default::default());
+
+    for (key, value) in vars {
+        let Some(key) = key.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+
+        let path: Vec<String> = key
+            .split(ENV_SEPARATOR)
+            .map(|segment| segment.to_lowercase())
+            .collect();
+
+        if !path
+            .first()
+            .is_some_and(|section| ENV_SECTIONS.contains(&section.as_str()))
+        {
+            continue;
+        }
+
+        let field = path.last().map(String::as_str).unwrap_or_default();
+        let value = if value.starts_with('{') || ENV_NON_STRING_KEYS.contains(&field) {
+            serde_json::from_str(&value)
+                .with_context(|| format!("failed to parse {ENV_PREFIX}{key}"))?
+        } else {
+            Value::String(value)
+        };
+
+        // Build the nested value for the path
+        let value = path
+            .iter()
+            .rev()
+            .fold(value, |value, segment| json!({ segment: value }));
+
+        merge_config_values(&mut config, value);
+    }
+
+    Ok(config)
+}
+
+/// Merge `overlay` into `base`, objects are merged recursively while any
+/// other values in `overlay` replace the value in `base`
+fn merge_config_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Serialize the config with any secret values redacted
+pub fn redact_config(config: &ServerConfigData) -> eyre::Result<Value> {
+    let mut value = serde_json::to_value(config)?;
+    redact_secret_keys(&mut value);
+
+    // In-memory secrets manager stores the secret values directly
+    if let Some(secrets) = value.get_mut("secrets").and_then(Value::as_object_mut) {
+        if let Some(values) = secrets.get_mut("secrets").and_then(Value::as_object_mut) {
+            values
+                .values_mut()
+                .for_each(|value| *value = Value::from(REDACTED));
+        }
+
+        if let Some(default) = secrets.get_mut("default").filter(|value| !value.is_null()) {
+            *default = Value::from(REDACTED);
+        }
+    }
+
+    Ok(value)
+}
+
+fn redact_secret_keys(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::from(REDACTED);
+                } else {
+                    redact_secret_keys(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_secret_keys),
+        _ => {}
+    }
+}
+
+/// Outcome of checking a backend connection
+#[derive(Serialize)]
+pub struct ConfigCheck {
+    pub backend: &'static str,
+    pub error: Option<String>,
+}
+
+/// Check the connection to each of the backends within the config
+pub async fn validate_config(
+    aws_config: &SdkConfig,
+    config: &ServerConfigData,
+) -> Vec<ConfigCheck> {
+    let secrets = SecretManager::from_config(aws_config, config.secrets.clone());
+
+    let checks = [
+        ("api", check_api(config).await),
+        ("secrets", check_secrets(&secrets).await),
+        ("database", check_database(&secrets, config).await),
+        ("search", check_search(aws_config, &secrets, config).await),
+        ("storage", check_storage(aws_config, config).await),
+    ];
+
+    checks
+        .into_iter()
+        .map(|(backend, result)| ConfigCheck {
+            backend,
+            error: result.err().map(|error| format!("{error:#}")),
+        })
+        .collect()
+}
+
+async fn check_api(config: &ServerConfigData) -> eyre::Result<()> {
+    // Any response means the server is reachable
+    reqwest::Client::new()
+        .get(&config.api.url)
+        .send()
+        .await
+        .context("failed to reach docbox api")?;
+
+    Ok(())
+}
+
+async fn check_secrets(secrets: &SecretManager) -> eyre::Result<()> {
+    secrets
+        .has_secret("docbox-cli-validate")
+        .await
+        .context("failed to access secrets manager")?;
+
+    Ok(())
+}
+
+async fn check_database(secrets: &SecretManager, config: &ServerConfigData) -> eyre::Result<()> {
+    let setup_user: AdminDatabaseSetupUserConfig = match (
+        config.database.setup_user.as_ref(),
+        config.database.setup_user_secret_name.as_deref(),
+    ) {
+        (Some(setup_user), _) => setup_user.clone(),
+        (_, Some(setup_user_secret_name)) => secrets
+            .parsed_secret(setup_user_secret_name)
+            .await
+            .context("failed to get setup user secret")?
+            .context("setup user secret not found")?,
+        (None, None) => {
+            eyre::bail!("must provided either setup_user or setup_user_secret_name")
+        }
+    };
+
+    let options = PgConnectOptions::new()
+        .host(&config.database.host)
+        .port(config.database.port)
+        .username(&setup_user.username)
+        .password(&setup_user.password)
+        .database("postgres");
+
+    let db = PgPool::connect_with(options)
+        .await
+        .context("failed to connect to database")?;
+
+    let _guard = close_pool_on_drop(&db);
+
+    sqlx::query("SELECT 1")
+        .execute(&db)
+        .await
+        .context("failed to query database")?;
+
+    Ok(())
+}
+
+async fn check_search(
+    aws_config: &SdkConfig,
+    secrets: &SecretManager,
+    config: &ServerConfigData,
+) -> eyre::Result<()> {
+    SearchClient::from_config(aws_config, secrets, &config.search)
+        .await?
+        .list_indexes()
+        .await?;
+
+    Ok(())
+}
+
+async fn check_storage(aws_config: &SdkConfig, config: &ServerConfigData) -> eyre::Result<()> {
+    StorageClient::from_config(aws_config, &config.storage)
+        .list_buckets()
+        .await?;
+
+    Ok(())
+}
+
+/// Interactively build a starter config for a local or AWS deployment
+pub fn init_config() -> eyre::Result<ServerConfigData> {
+    let local = Select::new()
+        .with_prompt("Deployment type")
+        .items(["Local", "AWS"])
+        .default(0)
+        .interact()?
+        == 0;
+
+    let api_url = prompt("Docbox API URL", local.then_some("http://localhost:8080"))?;
+    let api_key = prompt_optional("Docbox API key (Leave empty for none)")?;
+
+    let db_host = prompt("Database host", local.then_some("localhost"))?;
+    let db_port: u16 = Input::new()
+        .with_prompt("Database port")
+        .default(5432)
+        .interact_text()?;
+
+    let mut database = json!({
+        "host": db_host,
+        "port": db_port,
+    });
+
+    if local {
+        database["setup_user"] = json!({
+            "username": prompt("Database setup username", Some("postgres"))?,
+            "password": prompt("Database setup password", Some("postgres"))?,
+        });
+    } else {
+        database["setup_user_secret_name"] =
+            Value::from(prompt("Database setup user secret name", None)?);
+    }
+
+    if !local
+        && Confirm::new()
+            .with_prompt("Use IAM authentication for the root database?")
+            .default(true)
+            .interact()?
+    {
+        database["root_iam"] = Value::from(true);
+    } else {
+        database["root_secret_name"] = Value::from(prompt(
+            "Root database secret name",
+            (!local).then_some("postgres/docbox/config"),
+        )?);
+    }
+
+    let secrets = if local {
+        json!({
+            "provider": "aws",
+            "endpoint": {
+                "type": "custom",
+                "endpoint": prompt("Secrets manager endpoint", Some("http://localhost:4566"))?,
+                "access_key_id": prompt("Secrets manager access key ID", Some("test"))?,
+                "access_key_secret": prompt("Secrets manager access key secret", Some("test"))?,
+            }
+        })
+    } else {
+        json!({
+            "provider": "aws",
+            "endpoint": { "type": "aws" }
+        })
+    };
+
+    let search = match Select::new()
+        .with_prompt("Search backend")
+        .items(["Typesense", "OpenSearch", "Database"])
+        .default(if local { 0 } else { 1 })
+        .interact()?
+    {
+        0 => {
+            let url = prompt("Typesense URL", local.then_some("http://localhost:8108"))?;
+            if local {
+                json!({
+                    "provider": "typesense",
+                    "url": url,
+                    "api_key": prompt("Typesense API key", Some("typesensedev"))?,
+                })
+            } else {
+                json!({
+                    "provider": "typesense",
+                    "url": url,
+                    "api_key_secret_name": prompt("Typesense API key secret name", None)?,
+                })
+            }
+        }
+        1 => json!({
+            "provider": "open_search",
+            "url": prompt("OpenSearch URL", local.then_some("http://localhost:9200"))?,
+        }),
+        _ => json!({ "provider": "database" }),
+    };
+
+    let storage = if local {
+        let endpoint = prompt("S3 endpoint", Some("http://localhost:9090"))?;
+        json!({
+            "provider": "s3",
+            "endpoint": {
+                "type": "custom",
+                "external_endpoint": prompt("S3 external endpoint", Some(&endpoint))?,
+                "endpoint": endpoint,
+                "access_key_id": prompt("S3 access key ID", Some("minioadmin"))?,
+                "access_key_secret": prompt("S3 access key secret", Some("minioadmin"))?,
+            }
+        })
+    } else {
+        json!({
+            "provider": "s3",
+            "endpoint": { "type": "aws" }
+        })
+    };
+
+    let mut api = json!({ "url": api_url });
+    if let Some(api_key) = api_key {
+        api["api_key"] = Value::from(api_key);
+    }
+
+    let config = serde_json::from_value(json!({
+        "api": api,
+        "database": database,
+        "secrets": secrets,
+        "search": search,
+        "storage": storage,
+    }))
+    .context("failed to create config")?;
+
+    Ok(config)
+}
+
+fn prompt(prompt: &str, default: Option<&str>) -> eyre::Result<String> {
+    let mut input = Input::<String>::new().with_prompt(prompt);
+    if let Some(default) = default {
+        input = input.default(default.to_string());
+    }
+
+    Ok(input.interact_text()?)
+}
+
+fn prompt_optional(prompt: &str) -> eyre::Result<Option<String>> {
+    let value: String = Input::new()
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()?;
+
+    Ok(Some(value).filter(|value| !value.is_empty()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ErrorCode, REDACTED, env_config_value, merge_config_values, parse_unknown_config_format,
+        redact_config,
+    };
+    use docbox_management::config::ServerConfigData;
+    use serde_json::json;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Tests that objects are merged recursively with the overlay taking priority
+    #[test]
+    fn test_merge_config_values() {
+        let mut base = json!({
+            "api": { "url": "http://base", "api_key": "base-key" },
+            "database": { "host": "base", "port": 5432 }
+        });
+
+        merge_config_values(
+            &mut base,
+            json!({
+                "api": { "url": "http://overlay" },
+                "search": { "provider": "database" }
+            }),
+        );
+
+        assert_eq!(
+            base,
+            json!({
+                "api": { "url": "http://overlay", "api_key": "base-key" },
+                "database": { "host": "base", "port": 5432 },
+                "search": { "provider": "database" }
+            })
+        );
+    }
+
+    /// Tests that non object values replace the base value entirely
+    #[test]
+    fn test_merge_config_values_replace() {
+        let mut base = json!({ "search": { "provider": "typesense", "url": "http://base" } });
+        merge_config_values(&mut base, json!({ "search": "replaced" }));
+        assert_eq!(base, json!({ "search": "replaced" }));
+
+        let mut base = json!({ "list": [1, 2] });
+        merge_config_values(&mut base, json!({ "list": [3] }));
+        assert_eq!(base, json!({ "list": [3] }));
+    }
+
+    /// Tests the mapping of environment variables to nested config values
+    #[test]
+    fn test_env_config_value() {
+        let config = env_config_value(vars(&[
+            ("DOCBOX_DATABASE__HOST", "localhost"),
+            ("DOCBOX_DATABASE__PORT", "5433"),
+            ("DOCBOX_DATABASE__ROOT_IAM", "true"),
+            ("DOCBOX_DATABASE__SETUP_USER__USERNAME", "postgres"),
+            ("DOCBOX_STORAGE__ENDPOINT", r#"{"type":"aws"}"#),
+            ("DOCBOX_API__URL", "http://localhost:8080"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config,
+            json!({
+                "api": { "url": "http://localhost:8080" },
+                "database": {
+                    "host": "localhost",
+                    "port": 5433,
+                    "root_iam": true,
+                    "setup_user": { "username": "postgres" }
+                },
+                "storage": { "endpoint": { "type": "aws" } }
+            })
+        );
+    }
+
+    /// Tests that unrelated environment variables are ignored
+    #[test]
+    fn test_env_config_value_ignored() {
+        let config = env_config_value(vars(&[
+            ("PATH", "/usr/bin"),
+            ("DOCBOX_UNKNOWN__FIELD", "value"),
+            ("DOCBOX_LOG", "debug"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config, json!({}));
+    }
+
+    /// Tests that invalid non string values are reported
+    #[test]
+    fn test_env_config_value_invalid() {
+        assert!(env_config_value(vars(&[("DOCBOX_DATABASE__PORT", "not-a-port")])).is_err());
+    }
+
+    /// Tests that configs without an extension are parsed from any known format
+    #[test]
+    fn test_parse_unknown_config_format() {
+        let expected = json!({ "api": { "url": "http://localhost:8080" } });
+
+        for raw in [
+            r#"{"api":{"url":"http://localhost:8080"}}"#,
+            "[api]\nurl = \"http://localhost:8080\"",
+            "api:\n  url: http://localhost:8080",
+        ] {
+            assert_eq!(parse_unknown_config_format(raw).unwrap(), expected);
+        }
+    }
+
+    /// Tests that text which only parses as a single value is reported as a parse failure
+    #[test]
+    fn test_parse_unknown_config_format_invalid() {
+        let error = parse_unknown_config_format("not a config").unwrap_err();
+        assert_eq!(ErrorCode::classify(&error), ErrorCode::ConfigParseFailed);
+
+        let message = format!("{error:#}");
+        assert!(message.contains("json:"));
+        assert!(message.contains("toml:"));
+        assert!(message.contains("yaml:"));
+    }
+
+    /// Tests that secret values are redacted while other values are kept
+    #[test]
+    fn test_redact_config() {
+        let config: ServerConfigData = serde_json::from_value(json!({
+            "api": { "url": "http://localhost:8080", "api_key": "api-key" },
+            "database": {
+                "host": "localhost",
+                "port": 5432,
+                "setup_user": { "username": "postgres", "password": "password" },
+                "root_secret_name": "postgres/docbox/config"
+            },
+            "secrets": {
+                "provider": "memory",
+                "secrets": { "postgres/docbox/config": "secret-value" },
+                "default": "default-value"
+            },
+            "search": { "provider": "typesense", "url": "http://localhost:8108", "api_key": "typesense-key" },
+            "storage": {
+                "provider": "s3",
+                "endpoint": {
+                    "type": "custom",
+                    "endpoint": "http://localhost:9090",
+                    "external_endpoint": "http://localhost:9090",
+                    "access_key_id": "minioadmin",
+                    "access_key_secret": "minioadmin"
+                }
+            }
+        }))
+        .unwrap();
+
+        let value = redact_config(&config).unwrap();
+
+        assert_eq!(value["api"]["url"], "http://localhost:8080");
+        assert_eq!(value["api"]["api_key"], REDACTED);
+        assert_eq!(value["database"]["setup_user"]["username"], "postgres");
+        assert_eq!(value["database"]["setup_user"]["password"], REDACTED);
+        assert_eq!(
+            value["database"]["root_secret_name"],
+            "postgres/docbox/config"
+        );
+        assert_eq!(
+            value["secrets"]["secrets"]["postgres/docbox/config"],
+            REDACTED
+        );
+        assert_eq!(value["secrets"]["default"], REDACTED);
+        assert_eq!(value["search"]["api_key"], REDACTED);
+        assert_eq!(value["storage"]["endpoint"]["access_key_id"], "minioadmin");
+        assert_eq!(value["storage"]["endpoint"]["access_key_secret"], REDACTED);
+    }
+
+    /// Tests that missing secret values are not replaced with a redacted value
+    #[test]
+    fn test_redact_config_missing_secrets() {
+        let config: ServerConfigData = serde_json::from_value(json!({
+            "api": { "url": "http://localhost:8080" },
+            "database": { "host": "localhost", "port": 5432, "root_iam": true },
+            "secrets": { "provider": "memory" },
+            "search": { "provider": "database" },
+            "storage": { "provider": "s3", "endpoint": { "type": "aws" } }
+        }))
+        .unwrap();
+
+        let value = redact_config(&config).unwrap();
+
+        assert!(value["api"]["api_key"].is_null());
+        assert!(value["secrets"]["default"].is_null());
+    }
+}
@@ -0,0 +1,148 @@
+//! Named contexts for switching between docbox deployments
+//!
+//! Contexts are stored in `~/.config/docbox-cli/contexts.toml`:
+//!
+//! ```toml
+//! current = "production"
+//!
+//! [contexts.local]
+//! config = "/path/to/local.json"
+//!
+//! [contexts.production]
+//! aws_config_secret = "docbox/cli/production"
+//! ```
+
+use eyre::{Context, ContextCompat};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Contents of the contexts file
+#[derive(Default, Serialize, Deserialize)]
+pub struct ContextsFile {
+    /// Name of the active context
+    pub current: Option<String>,
+    /// Available contexts by name
+    #[serde(default)]
+    pub contexts: BTreeMap<String, CliContext>,
+}
+
+/// Deployment that a context points to
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CliContext {
+    /// Path to the cli configuration file
+    pub config: Option<PathBuf>,
+    /// Name of a AWS secret manager secret containing the cli configuration
+    pub aws_config_secret: Option<String>,
+}
+
+impl std::fmt::Display for CliContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.config, &self.aws_config_secret) {
+            (Some(config), _) => write!(f, "config: {}", config.display()),
+            (_, Some(aws_config_secret)) => write!(f, "secret: {aws_config_secret}"),
+            _ => f.write_str("None"),
+        }
+    }
+}
+
+/// Where the cli configuration should be loaded from
+#[derive(Default, Clone)]
+pub struct ConfigSource {
+    pub config: Vec<PathBuf>,
+    pub aws_config_secret: Option<String>,
+    /// Name of the context the source came from
+    pub context: Option<String>,
+}
+
+impl ContextsFile {
+    /// Path to the contexts file
+    pub fn path() -> eyre::Result<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_dir) => PathBuf::from(config_dir),
+            None => dirs::home_dir()
+                .context("failed to determine home directory")?
+                .join(".config"),
+        };
+
+        Ok(config_dir.join("docbox-cli").join("contexts.toml"))
+    }
+
+    /// Load the contexts file, providing an empty file if it does not exist
+    pub async fn load() -> eyre::Result<Self> {
+        let path = Self::path()?;
+        if !tokio::fs::try_exists(&path).await? {
+            return Ok(Self::default());
+        }
+
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Name of the current context, read synchronously for use within shell
+    /// completions where a missing or invalid file provides no context
+    pub fn current_name() -> Option<String> {
+        let contents = std::fs::read_to_string(Self::path().ok()?).ok()?;
+        toml::from_str::<Self>(&contents).ok()?.current
+    }
+
+    /// Save the contexts file
+    pub async fn save(&self) -> eyre::Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let contents = toml::to_string_pretty(self)?;
+        tokio::fs::write(&path, contents)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Get a context by `name`
+    pub fn get(&self, name: &str) -> eyre::Result<&CliContext> {
+        self.contexts
+            .get(name)
+            .with_context(|| format!("context {name} does not exist"))
+    }
+}
+
+/// Resolve where to load the configuration from, an explicit `config` or
+/// `aws_config_secret` takes priority over the `context` and then the
+/// current context
+pub async fn resolve_config_source(
+    config: Vec<PathBuf>,
+    aws_config_secret: Option<String>,
+    context: Option<String>,
+) -> eyre::Result<ConfigSource> {
+    if !config.is_empty() || aws_config_secret.is_some() {
+        return Ok(ConfigSource {
+            config,
+            aws_config_secret,
+            context: None,
+        });
+    }
+
+    let contexts = ContextsFile::load().await?;
+    let Some(name) = context.or_else(|| contexts.current.clone()) else {
+        return Ok(ConfigSource::default());
+    };
+
+    let context = contexts.get(&name)?;
+
+    // Relative paths are relative to the contexts file
+    let config = match (&context.config, ContextsFile::path()?.parent()) {
+        (Some(config), Some(parent)) => vec![parent.join(config)],
+        (config, _) => config.iter().cloned().collect(),
+    };
+
+    Ok(ConfigSource {
+        config,
+        aws_config_secret: context.aws_config_secret.clone(),
+        context: Some(name),
+    })
+}
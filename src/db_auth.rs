@@ -0,0 +1,327 @@
+//! Management of the credentials tenants use to access their databases
+
+use crate::secrets::SecretsClient;
+use docbox_management::{
+    config::AdminDatabaseConfiguration,
+    core::secrets::SecretManager,
+    database::{
+        DatabaseProvider, DbPool, DbSecrets, PgConnectOptions, PgPool, ROOT_DATABASE_NAME,
+        close_pool_on_drop,
+        create::{check_database_role_exists, make_role_iam_only},
+        models::tenant::Tenant,
+        sqlx,
+    },
+    password::random_password,
+};
+use eyre::{Context, ContextCompat};
+use serde_json::json;
+
+/// Length of the generated tenant database passwords
+const PASSWORD_LENGTH: usize = 30;
+
+/// Role granting IAM database authentication on RDS
+const RDS_IAM_ROLE: &str = "rds_iam";
+
+/// Rotate the password of a tenant that uses secret based database authentication
+///
+/// Sets a new password on the tenant database role, stores it in the tenant secret
+/// and checks the new credentials can connect to the tenant database
+pub async fn rotate_tenant_db_secret(
+    db_provider: &impl DatabaseProvider,
+    secrets: &SecretManager,
+    db_config: &AdminDatabaseConfiguration,
+    tenant: &Tenant,
+) -> eyre::Result<()> {
+    let secret_name = tenant
+        .db_secret_name
+        .as_ref()
+        .context("tenant does not use secret based database authentication")?;
+
+    let previous: DbSecrets = secrets
+        .parsed_secret(secret_name)
+        .await
+        .context("failed to get tenant database secret")?
+        .context("tenant database secret not found")?;
+
+    let password = generate_password();
+
+    let root_db = db_provider
+        .connect(ROOT_DATABASE_NAME)
+        .await
+        .context("failed to connect to root db")?;
+
+    let _guard = close_pool_on_drop(&root_db);
+
+    set_role_password(&root_db, &previous.username, &password)
+        .await
+        .context("failed to set tenant database role password")?;
+
+    if let Err(error) = set_db_secret(secrets, secret_name, &previous.username, &password).await {
+        // Restore the previous password so the stored secret remains valid
+        if let Err(error) =
+            set_role_password(&root_db, &previous.username, &previous.password).await
+        {
+            tracing::error!(
+                ?error,
+                "failed to restore previous tenant database password"
+            );
+        }
+
+        return Err(error);
+    }
+
+    verify_db_credentials(db_config, &tenant.db_name, &previous.username, &password).await?;
+
+    Ok(())
+}
+
+/// Migrate a tenant from IAM based database authentication back to secret
+/// based authentication
+///
+/// Gives the tenant database role a password stored in the new `secret_name` secret
+/// and removes its IAM access before updating the tenant to use the secret. The
+/// role regains its IAM access and the secret is removed if any step after the
+/// role change fails. Requires an RDS database as IAM access is granted through
+/// the rds_iam role
+pub async fn migrate_tenant_iam_to_secret(
+    db_provider: &impl DatabaseProvider,
+    secrets: &SecretManager,
+    secrets_client: &SecretsClient,
+    db_config: &AdminDatabaseConfiguration,
+    tenant: &mut Tenant,
+    secret_name: &str,
+) -> eyre::Result<()> {
+    let role_name = tenant
+        .db_iam_user_name
+        .clone()
+        .context("tenant does not use IAM based database authentication")?;
+
+    let password = generate_password();
+
+    let root_db = db_provider
+        .connect(ROOT_DATABASE_NAME)
+        .await
+        .context("failed to connect to root db")?;
+
+    let _guard = close_pool_on_drop(&root_db);
+
+    // IAM authentication is only available on RDS, the role is needed to revoke
+    // IAM access and to restore it if the migration fails
+    let has_rds_iam = check_database_role_exists(&root_db, RDS_IAM_ROLE)
+        .await
+        .context("failed to check for the rds_iam role")?;
+    if !has_rds_iam {
+        eyre::bail!(
+            "migrating from IAM requires an RDS database, the {RDS_IAM_ROLE} role does not exist"
+        );
+    }
+
+    // Fails when the secret already exists, we don't want to override it
+    let secret_value = db_secret_value(&role_name, &password)?;
+    secrets_client
+        .create_secret(secret_name, &secret_value)
+        .await
+        .context("failed to create tenant database secret")?;
+
+    let sql = format!(
+        r#"
+-- Allow password authentication
+ALTER ROLE "{role_name}" WITH PASSWORD '{password}';
+
+-- Remove IAM access, IAM roles cannot use password authentication
+REVOKE rds_iam FROM "{role_name}";
+    "#
+    );
+
+    if let Err(error) = sqlx::raw_sql(&sql).execute(&root_db).await {
+        delete_unused_secret(secrets, secret_name).await;
+        return Err(error).context("failed to set tenant database role password");
+    }
+
+    let result: eyre::Result<()> = async {
+        verify_db_credentials(db_config, &tenant.db_name, &role_name, &password).await?;
+
+        *tenant = set_tenant_db_secret(&root_db, tenant, secret_name).await?;
+
+        Ok(())
+    }
+    .await;
+
+    if let Err(error) = result {
+        // Restore IAM access so the tenant can still connect using its current config
+        if let Err(error) = make_role_iam_only(&root_db, &role_name).await {
+            tracing::error!(?error, "failed to restore tenant database role iam access");
+        }
+
+        delete_unused_secret(secrets, secret_name).await;
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Switch the `tenant` row to secret based authentication using `secret_name`,
+/// provides the updated tenant
+///
+/// [Tenant::update] keeps the current value for null fields so it cannot
+/// clear the IAM user name, the row is updated directly instead
+async fn set_tenant_db_secret(
+    db: &DbPool,
+    tenant: &Tenant,
+    secret_name: &str,
+) -> eyre::Result<Tenant> {
+    let updated: Tenant = sqlx::query_as(
+        r#"
+        UPDATE "docbox_tenants"
+        SET "db_iam_user_name" = NULL, "db_secret_name" = $1
+        WHERE "id" = $2 AND "env" = $3
+        RETURNING *
+        "#,
+    )
+    .bind(secret_name)
+    .bind(tenant.id)
+    .bind(&tenant.env)
+    .fetch_optional(db)
+    .await
+    .context("failed to update tenant")?
+    .context("tenant no longer exists")?;
+
+    check_secret_auth(&updated, secret_name)?;
+
+    Ok(updated)
+}
+
+/// Check that the `tenant` uses the `secret_name` secret rather than IAM,
+/// the API server uses IAM whenever the tenant has an IAM user name
+fn check_secret_auth(tenant: &Tenant, secret_name: &str) -> eyre::Result<()> {
+    if tenant.db_iam_user_name.is_some() {
+        eyre::bail!("tenant still has an IAM user name after the update");
+    }
+
+    if tenant.db_secret_name.as_deref() != Some(secret_name) {
+        eyre::bail!("tenant database secret name was not updated");
+    }
+
+    Ok(())
+}
+
+/// Remove a tenant database secret created by a migration that did not complete
+async fn delete_unused_secret(secrets: &SecretManager, secret_name: &str) {
+    if let Err(error) = secrets.delete_secret(secret_name, true).await {
+        tracing::error!(?error, "failed to remove unused tenant database secret");
+    }
+}
+
+/// Store the database credentials for a tenant in the secret `secret_name`
+pub async fn set_db_secret(
+    secrets: &SecretManager,
+    secret_name: &str,
+    username: &str,
+    password: &str,
+) -> eyre::Result<()> {
+    let secret_value = db_secret_value(username, password)?;
+
+    secrets
+        .set_secret(secret_name, &secret_value)
+        .await
+        .context("failed to update tenant database secret")?;
+
+    Ok(())
+}
+
+/// Value of a tenant database secret
+fn db_secret_value(username: &str, password: &str) -> eyre::Result<String> {
+    Ok(serde_json::to_string(&json!({
+        "username": username,
+        "password": password
+    }))?)
+}
+
+/// Set the password of a database role
+///
+/// `db` - Should be the root database
+pub async fn set_role_password(db: &DbPool, role_name: &str, password: &str) -> eyre::Result<()> {
+    let sql = format!(r#"ALTER ROLE "{role_name}" WITH PASSWORD '{password}';"#);
+    sqlx::raw_sql(&sql).execute(db).await?;
+
+    Ok(())
+}
+
+/// Generate a new tenant database password, the password is alphanumeric so it
+/// can be used within the quoted password literal of [set_role_password]
+fn generate_password() -> String {
+    random_password(PASSWORD_LENGTH)
+}
+
+/// Check that the provided credentials can connect to the `db_name` database
+pub async fn verify_db_credentials(
+    db_config: &AdminDatabaseConfiguration,
+    db_name: &str,
+    username: &str,
+    password: &str,
+) -> eyre::Result<()> {
+    let options = PgConnectOptions::new()
+        .host(&db_config.host)
+        .port(db_config.port)
+        .username(username)
+        .password(password)
+        .database(db_name);
+
+    let db = PgPool::connect_with(options)
+        .await
+        .context("failed to connect to tenant db with the new credentials")?;
+
+    let _guard = close_pool_on_drop(&db);
+
+    sqlx::query("SELECT 1")
+        .execute(&db)
+        .await
+        .context("failed to query tenant db with the new credentials")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PASSWORD_LENGTH, check_secret_auth, generate_password};
+    use docbox_management::database::models::tenant::{Tenant, TenantId};
+
+    /// Tests that generated passwords are the expected length and only
+    /// contain characters that are safe within the password literal
+    #[test]
+    fn test_generate_password() {
+        let password = generate_password();
+        assert_eq!(password.len(), PASSWORD_LENGTH);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    /// Tests that each generated password is unique
+    #[test]
+    fn test_generate_password_unique() {
+        assert_ne!(generate_password(), generate_password());
+    }
+
+    fn tenant(db_secret_name: Option<&str>, db_iam_user_name: Option<&str>) -> Tenant {
+        Tenant {
+            id: TenantId::nil(),
+            name: "tenant".to_string(),
+            db_name: "docbox-tenant".to_string(),
+            db_secret_name: db_secret_name.map(String::from),
+            db_iam_user_name: db_iam_user_name.map(String::from),
+            s3_name: "docbox-tenant".to_string(),
+            os_index_name: "docbox-tenant".to_string(),
+            env: "dev".to_string(),
+            event_queue_url: None,
+        }
+    }
+
+    /// Tests that a migrated tenant must use the secret and have no IAM user
+    /// name, the API server would otherwise keep using IAM
+    #[test]
+    fn test_check_secret_auth() {
+        assert!(check_secret_auth(&tenant(Some("secret"), None), "secret").is_ok());
+        assert!(check_secret_auth(&tenant(Some("secret"), Some("user")), "secret").is_err());
+        assert!(check_secret_auth(&tenant(None, Some("user")), "secret").is_err());
+        assert!(check_secret_auth(&tenant(Some("other"), None), "secret").is_err());
+    }
+}
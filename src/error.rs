@@ -0,0 +1,131 @@
+//! Classification of errors into stable codes for scripts to branch on
+
+use docbox_management::database::sqlx;
+use serde::Serialize;
+
+/// Stable machine readable error codes
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Unclassified error
+    Internal,
+    /// No configuration was provided
+    ConfigMissing,
+    /// Configuration could not be parsed
+    ConfigParseFailed,
+    /// A backend (database, storage, search, secrets, api) could not be reached
+    BackendUnreachable,
+    /// The requested tenant does not exist
+    TenantNotFound,
+    /// The operation was cancelled by the user
+    Cancelled,
+    /// The operation failed for some of the targeted items, such as the
+    /// tenants of a batch command
+    PartialFailure,
+}
+
+impl ErrorCode {
+    /// Process exit code for the error class
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCode::Internal => 1,
+            ErrorCode::ConfigMissing | ErrorCode::ConfigParseFailed => 3,
+            ErrorCode::BackendUnreachable => 4,
+            ErrorCode::TenantNotFound => 5,
+            ErrorCode::Cancelled => 6,
+            ErrorCode::PartialFailure => 7,
+        }
+    }
+
+    /// Determine the code for an error, explicitly tagged errors take priority
+    /// over the errors found within the error chain
+    pub fn classify(error: &eyre::Report) -> Self {
+        if let Some(code) = error.downcast_ref::<ErrorCode>() {
+            return *code;
+        }
+
+        error
+            .chain()
+            .find_map(|error| {
+                if is_config_parse_error(error) {
+                    Some(ErrorCode::ConfigParseFailed)
+                } else if is_backend_unreachable(error) {
+                    Some(ErrorCode::BackendUnreachable)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorCode::Internal)
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ErrorCode::Internal => "internal error",
+            ErrorCode::ConfigMissing => "no configuration provided",
+            ErrorCode::ConfigParseFailed => "failed to parse config",
+            ErrorCode::BackendUnreachable => "backend unreachable",
+            ErrorCode::TenantNotFound => "tenant not found",
+            ErrorCode::Cancelled => "operation cancelled",
+            ErrorCode::PartialFailure => "operation failed for some items",
+        })
+    }
+}
+
+impl std::error::Error for ErrorCode {}
+
+fn is_config_parse_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.is::<serde_json::Error>()
+        || error.is::<serde_norway::Error>()
+        || error.is::<toml::de::Error>()
+}
+
+fn is_backend_unreachable(error: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<sqlx::Error>() {
+        return matches!(
+            error,
+            sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+        );
+    }
+
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return error.is_connect() || error.is_timeout();
+    }
+
+    if let Some(error) = error.downcast_ref::<aws_sdk_s3::error::ConnectorError>() {
+        return error.is_io() || error.is_timeout();
+    }
+
+    false
+}
+
+/// Structured error output for JSON mode
+#[derive(Serialize)]
+pub struct ErrorEnvelope {
+    pub code: ErrorCode,
+    /// Top level error message
+    pub message: String,
+    /// Full error context chain, outermost first
+    pub chain: Vec<String>,
+    /// Command that failed
+    pub command: Option<String>,
+    pub exit_code: i32,
+}
+
+impl ErrorEnvelope {
+    pub fn new(error: &eyre::Report, command: Option<String>) -> Self {
+        let code = ErrorCode::classify(error);
+
+        Self {
+            code,
+            message: error.to_string(),
+            chain: error.chain().map(|error| error.to_string()).collect(),
+            command,
+            exit_code: code.exit_code(),
+        }
+    }
+}
@@ -1,17 +1,32 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use aws_config::SdkConfig;
+use backends::Backends;
+use clap::{
+    ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    builder::NonEmptyStringValueParser,
+};
+use clap_complete::{ArgValueCandidates, CompleteEnv, Shell};
+use columns::{TableArgs, TenantColumn, TenantRow};
 use comfy_table::{Cell, Table, modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL};
+use completion::{complete_env, complete_tenant_id, update_tenant_cache};
+use config::{init_config, load_config, redact_config, validate_config};
+use context::{ConfigSource, ContextsFile, resolve_config_source};
+use db_auth::{migrate_tenant_iam_to_secret, rotate_tenant_db_secret};
 use docbox_management::{
-    config::{ServerConfigData, load_server_config_data_secret},
+    config::ServerConfigData,
     core::{
         aws::aws_config,
+        storage::{CreateBucketOutcome, StorageLayerFactory, StorageLayerOptions},
         tenant::{
             rebuild_tenant_index::{rebuild_tenant_index, recreate_search_index_data},
             tenant_options_ext::TenantOptionsExt,
         },
     },
-    database::{DatabaseProvider, close_pool_on_drop, models::tenant::TenantId},
-    server::{ManagedServer, load_managed_server},
+    database::{
+        DatabaseProvider, ROOT_DATABASE_NAME, close_pool_on_drop,
+        models::tenant::{Tenant, TenantId, UpdateTenant},
+    },
     tenant::{
+        MigrateTenantsOutcome, TenantTarget,
         create_tenant::CreateTenantConfig,
         delete_tenant::{DeleteTenant, DeleteTenantOptions},
         flush_tenant_cache::flush_tenant_cache,
@@ -22,11 +37,40 @@ use docbox_management::{
         migrate_tenants_storage::{MigrateTenantsStorageConfig, migrate_tenants_storage},
     },
 };
+use error::{ErrorCode, ErrorEnvelope};
 use eyre::{Context, ContextCompat};
+use orphans::{OrphanedResource, ResourceKind, delete_orphan, find_orphans};
+use output::{OutputArgs, OutputFormat, print_output, print_output_rows};
+use root::get_root_status;
+use secrets::SecretsClient;
+use serde::Serialize;
 use serde_json::json;
+use shell::{ShellContext, run_shell};
 use std::path::PathBuf;
+use storage::{BucketUsage, CorsOriginsChange, CorsOriginsDiff, StorageClient, format_bytes};
+use tenant_detail::{TenantDetail, get_tenant_detail, get_tenant_live_detail};
+use tenants::{TenantFilter, TenantResource, find_tenants};
 use tracing_indicatif::IndicatifLayer;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tui::{TuiContext, run_tui};
+
+mod backends;
+mod columns;
+mod completion;
+mod config;
+mod context;
+mod db_auth;
+mod error;
+mod orphans;
+mod output;
+mod root;
+mod search;
+mod secrets;
+mod shell;
+mod storage;
+mod tenant_detail;
+mod tenants;
+mod tui;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -35,36 +79,71 @@ struct Args {
     pub command: Commands,
 
     /// Path to the cli configuration file if loading settings from a configuration
-    /// JSON file
+    /// JSON, YAML or TOML file, use "-" to read from stdin. Can be provided multiple
+    /// times with later files overriding earlier ones
+    ///
+    /// Values from DOCBOX_* environment variables override the loaded configuration,
+    /// nested fields are separated with a double underscore (DOCBOX_DATABASE__HOST)
     #[arg(short, long)]
-    pub config: Option<PathBuf>,
+    pub config: Vec<PathBuf>,
 
     /// Name of a AWS secret manager secret containing the cli configuration, used when
     /// loading a configuration from AWS secrets manager
     #[arg(short, long)]
     pub aws_config_secret: Option<String>,
 
-    #[arg(short, long, default_value = "human")]
-    pub format: OutputFormat,
-}
-
-#[derive(ValueEnum, Clone)]
-pub enum OutputFormat {
-    /// Provide output in human readable format
-    Human,
+    /// Name of a context from the contexts file to load the cli configuration from,
+    /// defaults to the current context when no configuration is provided
+    #[arg(long, conflicts_with_all = ["config", "aws_config_secret"])]
+    pub context: Option<String>,
 
-    /// Provide output in machine readable JSON format
-    Json,
+    #[command(flatten)]
+    pub output: OutputArgs,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Manage the named contexts for switching between deployments
+    Context {
+        #[command(subcommand)]
+        command: ContextCommand,
+    },
+
+    /// Manage the cli configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Generate a shell completion script
+    ///
+    /// Dynamic completion of environments and tenant IDs from the tenants cached
+    /// by get-tenants is available by instead sourcing the output of
+    /// `COMPLETE=<shell> docbox-cli` (i.e `source <(COMPLETE=bash docbox-cli)`)
+    Completions {
+        /// Shell to generate the completion script for
+        shell: Shell,
+    },
+
+    /// Generate man pages for the cli and every subcommand
+    Man {
+        /// Directory to write the man pages to
+        #[arg(short, long, default_value = "man")]
+        output: PathBuf,
+    },
+
     /// Initialize the root docbox database
     CreateRoot,
 
     /// Check if the root docbox database is initialized
     CheckRoot,
 
+    /// Report the state of the root docbox database
+    ///
+    /// Includes the connection details, authentication mode, root migrations
+    /// and the number of tenants within each environment
+    RootStatus,
+
     /// Create a new tenant
     CreateTenant {
         /// File containing the tenant configuration details
@@ -75,11 +154,11 @@ pub enum Commands {
     /// Rebuild the tenant search index from its files
     RebuildTenantIndex {
         /// Environment of the tenant
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
 
         /// ID of the tenant to rebuild
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: TenantId,
 
         /// File to save the rebuilt index to in case of failure
@@ -90,10 +169,10 @@ pub enum Commands {
     /// Delete a tenant
     DeleteTenant {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// Specific tenant to delete
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: TenantId,
         /// Whether to delete data stored within the tenant
         #[arg(short = 'c', long)]
@@ -119,31 +198,60 @@ pub enum Commands {
 
     /// Get all tenants
     GetTenants {
-        // Environment to filter to
-        #[arg(short, long)]
-        env: Option<String>,
+        #[command(flatten)]
+        filter: TenantFilter,
+        #[command(flatten)]
+        table: TableArgs,
     },
 
     /// Get a tenant
     GetTenant {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
-        /// Specific tenant to delete
-        #[arg(short, long)]
+        /// Specific tenant to get
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: TenantId,
+        /// Check the tenant resources against the live backends, includes the
+        /// bucket CORS origins and search index document count
+        #[arg(short, long)]
+        live: bool,
+    },
+
+    /// Find tenants by name or by the name of one of their resources
+    ///
+    /// Matching tenants are output in the same detail format as get-tenant
+    #[command(group(clap::ArgGroup::new("search").required(true)))]
+    FindTenant {
+        /// Case insensitive part of the tenant name, or a pattern using glob wildcards (* or ?)
+        #[arg(short, long, group = "search")]
+        name: Option<String>,
+        /// Name of the tenant database
+        #[arg(long, group = "search")]
+        db_name: Option<String>,
+        /// Name of the tenant storage bucket
+        #[arg(long, group = "search")]
+        bucket: Option<String>,
+        /// Name of the tenant search index
+        #[arg(long, group = "search")]
+        index: Option<String>,
+        /// Name of the tenant database secret
+        #[arg(long, group = "search")]
+        secret: Option<String>,
     },
 
     /// Run a migration
     Migrate {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// Specific tenant to run against
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: Option<TenantId>,
         #[arg(short, long)]
         skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
     },
 
     /// Run a root migration
@@ -152,86 +260,388 @@ pub enum Commands {
     /// Run a search migration
     MigrateSearch {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// Optional Name of the migration
         #[arg(short, long)]
         name: Option<String>,
         /// Specific tenant to run against
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: Option<TenantId>,
         /// Skip failed migrations
         #[arg(short, long)]
         skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
     },
 
     /// Run a storage migration
     MigrateStorage {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// Optional Name of the migration
         #[arg(short, long)]
         name: Option<String>,
         /// Specific tenant to run against
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: Option<TenantId>,
         /// Skip failed migrations
         #[arg(short, long)]
         skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
     },
 
     /// Set the allowed CORS origins for a tenant
     /// (Overrides existing CORS configuration)
     SetAllowedStorageCorsOrigins {
         // Environment to target
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// ID of the tenant to target
+        #[arg(
+            short,
+            long,
+            required_unless_present = "all_tenants",
+            conflicts_with_all = ["columns", "sort_by"],
+            add = ArgValueCandidates::new(complete_tenant_id)
+        )]
+        tenant_id: Option<TenantId>,
+        /// Apply to every tenant in the environment
+        #[arg(long, conflicts_with = "tenant_id")]
+        all_tenants: bool,
+        /// Skip failed tenants when applying to every tenant
+        #[arg(short, long, requires = "all_tenants")]
+        skip_failed: bool,
+        /// Allowed origins to set
+        #[arg(short, long, required_unless_present = "allow_empty", value_parser = NonEmptyStringValueParser::new())]
+        origin: Vec<String>,
+        /// Remove every allowed origin, required to set an empty list of origins
+        #[arg(long, conflicts_with = "origin")]
+        allow_empty: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Get the allowed CORS origins for a tenant
+    GetStorageCors {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// ID of the tenant to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
+        tenant_id: TenantId,
+    },
+
+    /// Add allowed CORS origins for a tenant
+    /// (Keeps the existing CORS origins)
+    AddStorageCorsOrigin {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// ID of the tenant to target
+        #[arg(
+            short,
+            long,
+            required_unless_present = "all_tenants",
+            conflicts_with_all = ["columns", "sort_by"],
+            add = ArgValueCandidates::new(complete_tenant_id)
+        )]
+        tenant_id: Option<TenantId>,
+        /// Apply to every tenant in the environment
+        #[arg(long, conflicts_with = "tenant_id")]
+        all_tenants: bool,
+        /// Skip failed tenants when applying to every tenant
+        #[arg(short, long, requires = "all_tenants")]
+        skip_failed: bool,
+        /// Allowed origins to add
+        #[arg(short, long, required = true, value_parser = NonEmptyStringValueParser::new())]
+        origin: Vec<String>,
+        /// Replace the origins when the storage backend does not support reading
+        /// the existing origins, any existing origins are lost
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Remove allowed CORS origins for a tenant
+    RemoveStorageCorsOrigin {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// ID of the tenant to target
+        #[arg(
+            short,
+            long,
+            required_unless_present = "all_tenants",
+            conflicts_with_all = ["columns", "sort_by"],
+            add = ArgValueCandidates::new(complete_tenant_id)
+        )]
+        tenant_id: Option<TenantId>,
+        /// Apply to every tenant in the environment
+        #[arg(long, conflicts_with = "tenant_id")]
+        all_tenants: bool,
+        /// Skip failed tenants when applying to every tenant
+        #[arg(short, long, requires = "all_tenants")]
+        skip_failed: bool,
+        /// Allowed origins to remove
+        #[arg(short, long, required = true, value_parser = NonEmptyStringValueParser::new())]
+        origin: Vec<String>,
+        /// Replace the origins when the storage backend does not support reading
+        /// the existing origins, any existing origins are lost
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Report the storage usage of tenants
+    StorageUsage {
+        // Environment to filter to
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: Option<String>,
+        /// Specific tenant to report on
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
+        tenant_id: Option<TenantId>,
+        /// Number of the largest objects to report for each tenant
+        #[arg(short, long, default_value_t = 5)]
+        largest: usize,
+        /// Skip tenants whose storage usage could not be determined
         #[arg(short, long)]
+        skip_failed: bool,
+    },
+
+    /// Move the storage of a tenant to a new bucket
+    ///
+    /// Copies the bucket configuration and every object to the new bucket before
+    /// updating the tenant to use it. Running this again after an interruption
+    /// resumes the move, skipping objects that have already been copied
+    ///
+    /// Writes to the tenant storage should be stopped during the move. Objects
+    /// created in the old bucket before the tenant is updated are copied by a
+    /// final pass, but changes to objects that were already copied are lost
+    MoveTenantStorage {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// ID of the tenant to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: TenantId,
-        /// Allowed origins to set
+        /// Name of the bucket to move the tenant storage to
+        #[arg(long)]
+        to_bucket: String,
+        /// Name of the bucket the tenant storage is being moved from, only required
+        /// when resuming a move after the tenant was updated to use the new bucket
+        #[arg(long)]
+        from_bucket: Option<String>,
+        /// Whether to delete the old bucket and its contents once the move is complete
         #[arg(short, long)]
-        origin: Vec<String>,
+        delete_old_bucket: bool,
     },
 
-    /// Migrate tenants from secrets to IAM
-    MigrateTenantIam {
+    /// Find resources that are not owned by any tenant
+    ///
+    /// Lists the databases, storage buckets, search indexes and secrets visible
+    /// to the deployment whose name starts with the prefix and reports the ones
+    /// no tenant refers to
+    FindOrphans {
+        /// Kinds of resource to check (Defaults to every kind)
+        #[arg(short, long, value_delimiter = ',')]
+        kind: Vec<ResourceKind>,
+        /// Only check resources whose name starts with this prefix, other
+        /// applications sharing the account or database server would otherwise
+        /// have their resources reported
+        #[arg(short, long, value_parser = NonEmptyStringValueParser::new())]
+        prefix: String,
+        /// Delete the orphaned resources, confirming each resource
+        #[arg(short, long)]
+        delete: bool,
+        /// Skip the deletion confirmation prompts
+        #[arg(short, long, requires = "delete")]
+        yes: bool,
+        /// Whether when using AWS secrets manager to immediately delete orphaned
+        /// secrets or to allow them to be recoverable for a short period of time
+        #[arg(long, requires = "delete")]
+        permanently_delete_secret: bool,
+    },
+
+    /// Migrate tenants from IAM back to secrets
+    ///
+    /// Gives the tenant database role a password stored in a new secret and
+    /// removes its IAM access
+    MigrateTenantSecret {
         // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// Specific tenant to run against
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
+        tenant_id: Option<TenantId>,
+        /// Name of the secret to create for each tenant, "{env}", "{tenant_id}" and
+        /// "{db_name}" are replaced with the tenant values
+        #[arg(long, default_value = "docbox-{env}-{db_name}")]
+        secret_name: String,
+        /// Skip tenants that fail to migrate
         #[arg(short, long)]
+        skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Rotate the database password of tenants using secret based authentication
+    ///
+    /// Generates a new password for the tenant database role, stores it in the
+    /// tenant database secret and checks the new credentials before asking the
+    /// API to drop its pooled connections
+    RotateTenantDbSecret {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
         env: String,
         /// Specific tenant to run against
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
+        tenant_id: Option<TenantId>,
+        /// Skip tenants that fail to rotate
         #[arg(short, long)]
+        skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Migrate tenants from secrets to IAM
+    MigrateTenantIam {
+        // Environment to target
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: String,
+        /// Specific tenant to run against
+        #[arg(short, long, add = ArgValueCandidates::new(complete_tenant_id))]
         tenant_id: Option<TenantId>,
+        /// Skip tenants that fail to migrate
+        #[arg(short, long)]
+        skip_failed: bool,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// Interactive terminal interface for browsing tenants, checking their
+    /// health and running operations against them
+    Tui,
+
+    /// Interactive shell that loads the server once and then runs commands
+    /// against it, with history and tab completion
+    Shell {
+        /// Environment to use for commands that do not specify one, can be
+        /// changed within the shell using "env <env>"
+        #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+        env: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Parse the configuration and test the connection to each backend
+    Validate,
+
+    /// Print the configuration with secrets redacted
+    Show,
+
+    /// Interactively create a starter configuration file
+    Init {
+        /// Path to write the configuration file to
+        #[arg(short, long, default_value = "docbox-cli.json")]
+        output: PathBuf,
+        /// Overwrite the configuration file if it already exists
+        #[arg(long)]
+        force: bool,
     },
 }
 
+#[derive(Subcommand)]
+pub enum ContextCommand {
+    /// Switch the current context
+    Use {
+        /// Name of the context to use
+        name: String,
+    },
+
+    /// List the available contexts
+    List,
+
+    /// Show the current context
+    Current,
+}
+
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
-    let args = Args::parse();
-    let format = args.format.clone();
+    // Respond to dynamic shell completion requests
+    CompleteEnv::with_factory(Args::command).complete();
+
+    let matches = Args::command().get_matches();
+    let command = command_name(&matches);
+    let mut args = Args::from_arg_matches(&matches)?;
+    args.output = args.output.resolve();
+
+    let output = args.output.clone();
 
     if let Err(error) = app(args).await {
-        match format {
-            OutputFormat::Human => {
-                return Err(error);
-            }
-            OutputFormat::Json => {
-                tracing::error!(?error, "error occurred");
+        let exit_code = print_error(&output, &error, command)?;
+        std::process::exit(exit_code);
+    }
 
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&json!({
-                        "error": error.to_string()
-                    }))?
-                );
+    Ok(())
+}
 
-                return Err(error);
-            }
+/// Print an `error` in the output format, provides the exit code for the error
+fn print_error(
+    output: &OutputArgs,
+    error: &eyre::Report,
+    command: Option<String>,
+) -> eyre::Result<i32> {
+    // Errors are output without the query or template applied
+    let output = OutputArgs {
+        query: None,
+        single: false,
+        raw: false,
+        template: None,
+        ..output.clone()
+    };
+
+    let envelope = ErrorEnvelope::new(error, command);
+
+    match output.format {
+        // The outcome has already been output, another document would
+        // break the machine readable output
+        _ if envelope.code == ErrorCode::PartialFailure => {
+            eprintln!("Error: {error:#}");
+        }
+        OutputFormat::Human => {
+            eprintln!("Error: {error:?}");
+        }
+        _ => {
+            tracing::error!(?error, "error occurred");
+
+            print_output(
+                &output,
+                &json!({
+                    "error": envelope
+                }),
+            )?;
         }
     }
 
-    Ok(())
+    Ok(envelope.exit_code)
+}
+
+/// Full name of the invoked subcommand (i.e "config validate")
+fn command_name(matches: &ArgMatches) -> Option<String> {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        names.push(name);
+        matches = sub_matches;
+    }
+
+    (!names.is_empty()).then(|| names.join(" "))
 }
 
 async fn app(args: Args) -> eyre::Result<()> {
@@ -243,6 +653,9 @@ async fn app(args: Args) -> eyre::Result<()> {
 
     let indicatif_layer = IndicatifLayer::new();
 
+    // Logging to the terminal would draw over the interface
+    let log_to_terminal = !matches!(args.command, Commands::Tui);
+
     tracing_subscriber::registry()
         .with(
             EnvFilter::from_default_env()
@@ -260,71 +673,188 @@ async fn app(args: Args) -> eyre::Result<()> {
                 .add_directive("aws_smithy_runtime=info".parse()?)
                 .add_directive("hyper_util=info".parse()?),
         )
-        .with(
+        .with(log_to_terminal.then(|| {
             tracing_subscriber::fmt::layer()
                 .with_line_number(false)
                 .with_target(false)
                 .with_file(false)
-                .with_writer(indicatif_layer.get_stderr_writer()),
-        )
-        .with(indicatif_layer)
+                .with_writer(indicatif_layer.get_stderr_writer())
+        }))
+        .with(log_to_terminal.then_some(indicatif_layer))
         .init();
 
     let aws_config = aws_config().await;
 
-    // Load the config data
-    let config: ServerConfigData = match (args.config, args.aws_config_secret) {
-        (Some(config_path), _) => {
-            let config_raw = tokio::fs::read(config_path).await?;
-            let config: ServerConfigData =
-                serde_json::from_slice(&config_raw).context("failed to parse config")?;
-            config
+    let command = match args.command {
+        // Context commands manage the contexts file and do not need any config
+        Commands::Context { command } => {
+            return run_context_command(&args.output, command).await;
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Args::command(),
+                env!("CARGO_BIN_NAME"),
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        Commands::Man { output } => {
+            tokio::fs::create_dir_all(&output)
+                .await
+                .context("failed to create man page directory")?;
+            clap_mangen::generate_to(Args::command(), &output)
+                .context("failed to generate man pages")?;
+
+            if let OutputFormat::Human = args.output.format {
+                println!("generated man pages in {}", output.display());
+            }
+            return Ok(());
         }
-        (_, Some(config_secret_name)) => {
-            load_server_config_data_secret(&aws_config, &config_secret_name).await?
+        // Creating a config does not use the current config or context
+        Commands::Config {
+            command: ConfigCommand::Init { output, force },
+        } => {
+            return run_config_init(&args.output, output, force).await;
         }
+        command => command,
+    };
+
+    let source = resolve_config_source(args.config, args.aws_config_secret, args.context).await?;
+
+    if let (OutputFormat::Human, Some(context)) = (&args.output.format, &source.context) {
+        eprintln!("context: {context}");
+    }
 
-        _ => eyre::bail!(
-            "must provided either --config or --aws-config-secret check --help for more details"
-        ),
+    let command = match command {
+        // Config commands operate on the config itself rather than the server
+        Commands::Config { command } => {
+            return run_config_command(&aws_config, source, &args.output, command).await;
+        }
+        command => command,
     };
 
-    let ManagedServer {
-        db_cache,
-        db_provider,
-        secrets,
-        search,
-        storage,
-        events,
-    } = load_managed_server(&aws_config, &config).await.unwrap();
+    // Load the config data
+    let config = load_config(&aws_config, source.clone()).await?;
+
+    let backends = Backends::new(&aws_config, &config);
+
+    if let Commands::Shell { env } = command {
+        return run_shell(
+            ShellContext {
+                aws_config: &aws_config,
+                config: &config,
+                backends: &backends,
+                source: &source,
+            },
+            env,
+        )
+        .await;
+    }
+
+    run_command(
+        &aws_config,
+        &config,
+        &source,
+        &backends,
+        &args.output,
+        command,
+    )
+    .await
+}
+
+/// Run a `command` against the server backends, `source` is where the
+/// `config` was loaded from
+async fn run_command(
+    aws_config: &SdkConfig,
+    config: &ServerConfigData,
+    source: &ConfigSource,
+    backends: &Backends<'_>,
+    output: &OutputArgs,
+    command: Commands,
+) -> eyre::Result<()> {
+    match command {
+        Commands::Context { .. }
+        | Commands::Config { .. }
+        | Commands::Completions { .. }
+        | Commands::Man { .. }
+        | Commands::Shell { .. } => {
+            unreachable!(
+                "context, config, completions, man and shell commands are handled before running commands"
+            )
+        }
+
+        Commands::Tui => run_tui(TuiContext { config, backends }).await,
 
-    match args.command {
         Commands::CreateRoot => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+
+            let is_initialized = docbox_management::root::initialize::is_initialized(db_provider)
+                .await
+                .context("failed to check root initialized")?;
+
+            if is_initialized {
+                match output.format {
+                    OutputFormat::Human => {
+                        println!("root is already initialized, nothing to do");
+                    }
+                    _ => {
+                        print_output(
+                            output,
+                            &json!({
+                                "initialized": true,
+                                "created": false
+                            }),
+                        )?;
+                    }
+                }
+
+                return Ok(());
+            }
+
             if config.database.root_iam {
-                docbox_management::root::initialize::initialize_iam(&db_provider)
+                docbox_management::root::initialize::initialize_iam(db_provider)
                     .await
                     .context("failed to setup root (iam)")?;
             } else if let Some(root_secret_name) = config.database.root_secret_name.as_ref() {
                 docbox_management::root::initialize::initialize(
-                    &db_provider,
-                    &secrets,
+                    db_provider,
+                    secrets,
                     root_secret_name,
                 )
                 .await
                 .context("failed to setup root")?;
+            } else {
+                eyre::bail!(
+                    "root database authentication is not configured, set either database.root_iam or database.root_secret_name"
+                );
+            }
+
+            // Ensure every root migration has been applied
+            docbox_management::root::migrate_root::migrate_root(db_provider, None)
+                .await
+                .context("failed to migrate root")?;
+
+            if !docbox_management::root::initialize::is_initialized(db_provider)
+                .await
+                .context("failed to check root initialized")?
+            {
+                eyre::bail!("root is not initialized after setup");
             }
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     println!("successfully created root");
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
-                            "initialized": true
-                        }))?
-                    );
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
+                            "initialized": true,
+                            "created": true
+                        }),
+                    )?;
                 }
             }
 
@@ -332,11 +862,13 @@ async fn app(args: Args) -> eyre::Result<()> {
         }
 
         Commands::CheckRoot => {
-            let is_initialized = docbox_management::root::initialize::is_initialized(&db_provider)
+            let db_provider = backends.db_provider().await?;
+
+            let is_initialized = docbox_management::root::initialize::is_initialized(db_provider)
                 .await
                 .context("failed to setup root")?;
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     if is_initialized {
                         println!("root is initialized");
@@ -344,13 +876,104 @@ async fn app(args: Args) -> eyre::Result<()> {
                         println!("root is not initialized");
                     }
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
                             "is_initialized": is_initialized
-                        }))?
-                    );
+                        }),
+                    )?;
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::RootStatus => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+
+            let status = get_root_status(db_provider, secrets, &config.database).await?;
+
+            match output.format {
+                OutputFormat::Human => {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+
+                    let connection = &status.connection;
+                    table.add_row(vec![Cell::new("Host"), Cell::new(&connection.host)]);
+                    table.add_row(vec![Cell::new("Port"), Cell::new(connection.port)]);
+                    table.add_row(vec![Cell::new("Database"), Cell::new(connection.database)]);
+                    table.add_row(vec![
+                        Cell::new("Setup User"),
+                        Cell::new(
+                            match (&connection.setup_user, &connection.setup_user_secret_name) {
+                                (Some(username), _) => format!("{username} (password: <redacted>)"),
+                                (_, Some(secret_name)) => format!("secret: {secret_name}"),
+                                _ => "None".to_string(),
+                            },
+                        ),
+                    ]);
+                    table.add_row(vec![Cell::new("Root Auth"), Cell::new(status.auth_mode)]);
+                    if let Some(root_secret_name) = &status.root_secret_name {
+                        table.add_row(vec![
+                            Cell::new("Root Secret Name"),
+                            Cell::new(match status.root_secret_exists {
+                                Some(false) => format!("{root_secret_name} (missing)"),
+                                _ => root_secret_name.clone(),
+                            }),
+                        ]);
+                    }
+                    table.add_row(vec![
+                        Cell::new("Initialized"),
+                        Cell::new(status.initialized),
+                    ]);
+                    table.add_row(vec![
+                        Cell::new("Server Version"),
+                        Cell::new(&status.server_version),
+                    ]);
+
+                    println!("{table}");
+
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Migration", "Applied At"]);
+
+                    for migration in &status.applied_migrations {
+                        table.add_row(vec![
+                            Cell::new(&migration.name),
+                            Cell::new(migration.applied_at.to_rfc3339()),
+                        ]);
+                    }
+                    for migration in &status.pending_migrations {
+                        table.add_row(vec![Cell::new(migration), Cell::new("Pending")]);
+                    }
+
+                    println!("root migrations");
+                    println!("{table}");
+
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Env", "Tenants"]);
+
+                    for (env, count) in &status.tenant_counts {
+                        table.add_row(vec![Cell::new(env), Cell::new(count)]);
+                    }
+
+                    println!("tenants");
+                    println!("{table}");
+                }
+                _ => {
+                    print_output(output, &status)?;
                 }
             }
 
@@ -358,6 +981,11 @@ async fn app(args: Args) -> eyre::Result<()> {
         }
 
         Commands::CreateTenant { file } => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+            let search = backends.search().await?;
+            let storage = backends.storage().await;
+
             // Load the create tenant config
             let tenant_config_raw = tokio::fs::read(file).await?;
             let tenant_config: CreateTenantConfig =
@@ -366,17 +994,17 @@ async fn app(args: Args) -> eyre::Result<()> {
             tracing::info!(?tenant_config, "creating tenant");
 
             let tenant = docbox_management::tenant::create_tenant::create_tenant(
-                &db_provider,
-                &search,
-                &storage,
-                &secrets,
+                db_provider,
+                search,
+                storage,
+                secrets,
                 tenant_config,
             )
             .await?;
 
             tracing::info!(?tenant, "tenant created successfully");
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     println!("tenant created successfully");
 
@@ -394,8 +1022,8 @@ async fn app(args: Args) -> eyre::Result<()> {
 
                     println!("{table}")
                 }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&tenant)?);
+                _ => {
+                    print_output(output, &tenant)?;
                 }
             }
 
@@ -411,10 +1039,17 @@ async fn app(args: Args) -> eyre::Result<()> {
             delete_storage,
             permanently_delete_secret,
         } => {
+            let db_provider = backends.db_provider().await?;
+            let db_cache = backends.db_cache().await;
+            let secrets = backends.secrets().await;
+            let search = backends.search().await?;
+            let storage = backends.storage().await;
+            let events = backends.events().await;
+
             let tenant =
-                docbox_management::tenant::get_tenant::get_tenant(&db_provider, &env, tenant_id)
+                docbox_management::tenant::get_tenant::get_tenant(db_provider, &env, tenant_id)
                     .await?
-                    .context("tenant not found")?;
+                    .context(ErrorCode::TenantNotFound)?;
 
             // Must close the connections in advance to ensure the tenant
             // database can be deleted
@@ -426,11 +1061,11 @@ async fn app(args: Args) -> eyre::Result<()> {
                 .context("failed to flush tenant cache")?;
 
             docbox_management::tenant::delete_tenant::delete_tenant(
-                &db_provider,
-                &search,
-                &storage,
-                &events,
-                &secrets,
+                db_provider,
+                search,
+                storage,
+                events,
+                secrets,
                 DeleteTenant {
                     env,
                     tenant_id,
@@ -445,109 +1080,137 @@ async fn app(args: Args) -> eyre::Result<()> {
             )
             .await?;
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     println!("deleted tenant")
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
                             "deleted": true
-                        }))?
-                    );
+                        }),
+                    )?;
                 }
             }
 
             Ok(())
         }
 
-        Commands::GetTenants { env } => {
-            let mut tenants =
-                docbox_management::tenant::get_tenants::get_tenants(&db_provider).await?;
+        Commands::GetTenants { filter, table } => {
+            let db_provider = backends.db_provider().await?;
+
+            let tenants = find_tenants(db_provider, &filter, table.sort_by).await?;
 
-            if let Some(env) = env {
-                tenants.retain(|tenant| tenant.env.eq(&env));
+            // Keep the completion cache up to date when every tenant was loaded
+            if filter.is_unfiltered()
+                && let Err(error) = update_tenant_cache(source.context.as_deref(), &tenants).await
+            {
+                tracing::warn!(?error, "failed to update tenant completion cache");
             }
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
-                    let mut table = Table::new();
-                    table
+                    let mut output = Table::new();
+                    output
                         .load_preset(UTF8_FULL)
                         .apply_modifier(UTF8_ROUND_CORNERS)
                         .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-                        .set_header(vec!["ID", "Name", "Env"]);
+                        .set_header(table.headers());
 
-                    for tenant in tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                        ]);
+                    for tenant in &tenants {
+                        output.add_row(table.cells(tenant));
                     }
 
-                    println!("{table}")
+                    println!("{output}")
+                }
+                OutputFormat::Csv => {
+                    let rows: Vec<_> = tenants.iter().map(|tenant| table.row(tenant)).collect();
+                    print_output(output, &rows)?;
                 }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&tenants)?);
+                _ => {
+                    print_output(output, &tenants)?;
                 }
             }
 
             Ok(())
         }
 
-        Commands::GetTenant { env, tenant_id } => {
+        Commands::GetTenant {
+            env,
+            tenant_id,
+            live,
+        } => {
+            let db_provider = backends.db_provider().await?;
+
             let tenant =
-                docbox_management::tenant::get_tenant::get_tenant(&db_provider, &env, tenant_id)
+                docbox_management::tenant::get_tenant::get_tenant(db_provider, &env, tenant_id)
                     .await?
-                    .context("tenant not found")?;
+                    .context(ErrorCode::TenantNotFound)?;
+
+            let mut detail = get_tenant_detail(db_provider, tenant).await?;
+
+            if live {
+                detail.live = Some(
+                    get_tenant_live_detail(
+                        db_provider,
+                        backends.secrets().await,
+                        backends.storage_client().await,
+                        backends.search_client().await,
+                        backends.sqs_client().await,
+                        &detail.tenant,
+                    )
+                    .await?,
+                );
+            }
 
-            match args.format {
-                OutputFormat::Human => {
-                    let mut table = Table::new();
-                    table
-                        .load_preset(UTF8_FULL)
-                        .apply_modifier(UTF8_ROUND_CORNERS)
-                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+            match output.format {
+                OutputFormat::Human => print_tenant_detail(&detail),
+                _ => {
+                    print_output(output, &detail)?;
+                }
+            }
 
-                    table.add_row(vec![Cell::new("ID"), Cell::new(tenant.id.to_string())]);
-                    table.add_row(vec![Cell::new("Name"), Cell::new(tenant.name)]);
-                    table.add_row(vec![Cell::new("Env"), Cell::new(tenant.env)]);
-                    table.add_row(vec![Cell::new("DB Name"), Cell::new(tenant.db_name)]);
-                    table.add_row(vec![
-                        Cell::new("DB Secret Name"),
-                        Cell::new(if let Some(value) = tenant.db_secret_name {
-                            format!("Some({value}")
-                        } else {
-                            "None".to_string()
-                        }),
-                    ]);
-                    table.add_row(vec![
-                        Cell::new("DB IAM User Name"),
-                        Cell::new(if let Some(value) = tenant.db_iam_user_name {
-                            format!("Some({value}")
-                        } else {
-                            "None".to_string()
-                        }),
-                    ]);
-                    table.add_row(vec![
-                        Cell::new("Storage Bucket Name"),
-                        Cell::new(tenant.s3_name),
-                    ]);
-                    table.add_row(vec![
-                        Cell::new("Search Index Name"),
-                        Cell::new(tenant.os_index_name),
-                    ]);
-                    table.add_row(vec![
-                        Cell::new("Event Queue URL"),
-                        Cell::new(tenant.event_queue_url.unwrap_or_default()),
-                    ]);
+            Ok(())
+        }
 
-                    println!("{table}");
-                }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&tenant)?);
+        Commands::FindTenant {
+            name,
+            db_name,
+            bucket,
+            index,
+            secret,
+        } => {
+            let db_provider = backends.db_provider().await?;
+
+            // Only one of the search arguments is provided
+            let resource = db_name
+                .map(TenantResource::Database)
+                .or(bucket.map(TenantResource::Bucket))
+                .or(index.map(TenantResource::SearchIndex))
+                .or(secret.map(TenantResource::Secret));
+
+            let filter = TenantFilter {
+                name,
+                resource,
+                ..Default::default()
+            };
+
+            let tenants = find_tenants(db_provider, &filter, None).await?;
+
+            if tenants.is_empty() {
+                return Err(ErrorCode::TenantNotFound.into());
+            }
+
+            let mut details = Vec::with_capacity(tenants.len());
+            for tenant in tenants {
+                details.push(get_tenant_detail(db_provider, tenant).await?);
+            }
+
+            match output.format {
+                OutputFormat::Human => details.iter().for_each(print_tenant_detail),
+                _ => {
+                    print_output(output, &details)?;
                 }
             }
 
@@ -558,9 +1221,12 @@ async fn app(args: Args) -> eyre::Result<()> {
             env,
             tenant_id,
             skip_failed,
+            table,
         } => {
+            let db_provider = backends.db_provider().await?;
+
             let outcome = docbox_management::tenant::migrate_tenants::migrate_tenants(
-                &db_provider,
+                db_provider,
                 MigrateTenantsConfig {
                     env: Some(env),
                     tenant_id,
@@ -570,56 +1236,34 @@ async fn app(args: Args) -> eyre::Result<()> {
             )
             .await?;
 
-            match args.format {
-                OutputFormat::Human => {
-                    let mut table = Table::new();
-                    table
-                        .load_preset(UTF8_FULL)
-                        .apply_modifier(UTF8_ROUND_CORNERS)
-                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-                        .set_header(vec!["ID", "Name", "Env", "Outcome"]);
-
-                    for tenant in outcome.applied_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new("Success"),
-                        ]);
-                    }
-                    for (error, tenant) in outcome.failed_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new(format!("Failed: {error}")),
-                        ]);
-                    }
-
-                    println!("{table}")
-                }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&outcome)?);
-                }
+            if tenant_id.is_some()
+                && outcome.applied_tenants.is_empty()
+                && outcome.failed_tenants.is_empty()
+            {
+                return Err(ErrorCode::TenantNotFound.into());
             }
 
-            Ok(())
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, None).await?;
+            check_failed_tenants(failed)
         }
 
         Commands::MigrateRoot => {
-            docbox_management::root::migrate_root::migrate_root(&db_provider, None).await?;
+            let db_provider = backends.db_provider().await?;
 
-            match args.format {
+            docbox_management::root::migrate_root::migrate_root(db_provider, None).await?;
+
+            match output.format {
                 OutputFormat::Human => {
                     println!("Migrations applied")
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
                             "success": true
-                        }))?
-                    );
+                        }),
+                    )?;
                 }
             }
 
@@ -631,10 +1275,14 @@ async fn app(args: Args) -> eyre::Result<()> {
             name,
             tenant_id,
             skip_failed,
+            table,
         } => {
+            let db_provider = backends.db_provider().await?;
+            let search = backends.search().await?;
+
             let outcome = migrate_tenants_search(
-                &db_provider,
-                &search,
+                db_provider,
+                search,
                 MigrateTenantsSearchConfig {
                     env: Some(env),
                     tenant_id,
@@ -644,40 +1292,16 @@ async fn app(args: Args) -> eyre::Result<()> {
             )
             .await?;
 
-            match args.format {
-                OutputFormat::Human => {
-                    let mut table = Table::new();
-                    table
-                        .load_preset(UTF8_FULL)
-                        .apply_modifier(UTF8_ROUND_CORNERS)
-                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-                        .set_header(vec!["ID", "Name", "Env", "Outcome"]);
-
-                    for tenant in outcome.applied_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new("Success"),
-                        ]);
-                    }
-                    for (error, tenant) in outcome.failed_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new(format!("Failed: {error}")),
-                        ]);
-                    }
-
-                    println!("{table}")
-                }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&outcome)?);
-                }
+            if tenant_id.is_some()
+                && outcome.applied_tenants.is_empty()
+                && outcome.failed_tenants.is_empty()
+            {
+                return Err(ErrorCode::TenantNotFound.into());
             }
 
-            Ok(())
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, None).await?;
+            check_failed_tenants(failed)
         }
 
         Commands::MigrateStorage {
@@ -685,10 +1309,14 @@ async fn app(args: Args) -> eyre::Result<()> {
             name,
             tenant_id,
             skip_failed,
+            table,
         } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+
             let outcome = migrate_tenants_storage(
-                &db_provider,
-                &storage,
+                db_provider,
+                storage,
                 MigrateTenantsStorageConfig {
                     env: Some(env),
                     tenant_id,
@@ -698,40 +1326,16 @@ async fn app(args: Args) -> eyre::Result<()> {
             )
             .await?;
 
-            match args.format {
-                OutputFormat::Human => {
-                    let mut table = Table::new();
-                    table
-                        .load_preset(UTF8_FULL)
-                        .apply_modifier(UTF8_ROUND_CORNERS)
-                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-                        .set_header(vec!["ID", "Name", "Env", "Outcome"]);
-
-                    for tenant in outcome.applied_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new("Success"),
-                        ]);
-                    }
-                    for (error, tenant) in outcome.failed_tenants {
-                        table.add_row(vec![
-                            Cell::new(tenant.tenant_id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new(format!("Failed: {error}")),
-                        ]);
-                    }
-
-                    println!("{table}")
-                }
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&outcome)?);
-                }
+            if tenant_id.is_some()
+                && outcome.applied_tenants.is_empty()
+                && outcome.failed_tenants.is_empty()
+            {
+                return Err(ErrorCode::TenantNotFound.into());
             }
 
-            Ok(())
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, None).await?;
+            check_failed_tenants(failed)
         }
 
         Commands::RebuildTenantIndex {
@@ -739,9 +1343,13 @@ async fn app(args: Args) -> eyre::Result<()> {
             tenant_id,
             file,
         } => {
-            let tenant = get_tenant(&db_provider, &env, tenant_id)
+            let db_provider = backends.db_provider().await?;
+            let search = backends.search().await?;
+            let storage = backends.storage().await;
+
+            let tenant = get_tenant(db_provider, &env, tenant_id)
                 .await?
-                .context("tenant not found")?;
+                .context(ErrorCode::TenantNotFound)?;
 
             let search = search.create_search_index(&tenant);
             let storage = storage.create_layer(tenant.storage_layer_options());
@@ -773,88 +1381,1432 @@ async fn app(args: Args) -> eyre::Result<()> {
 
         Commands::SetAllowedStorageCorsOrigins {
             env,
-            tenant_id,
+            tenant_id: None,
+            skip_failed,
+            origin,
+            table,
+            ..
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+            tenants.retain(|tenant| tenant.env.eq(&env));
+
+            let outcome = update_all_tenants_cors_origins(
+                storage,
+                storage_client,
+                &tenants,
+                skip_failed,
+                CorsOriginsChange::Set(origin),
+                false,
+            )
+            .await;
+
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, Some(tenants)).await?;
+            check_failed_tenants(failed)
+        }
+
+        Commands::AddStorageCorsOrigin {
+            env,
+            tenant_id: None,
+            skip_failed,
+            origin,
+            force,
+            table,
+            ..
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+            tenants.retain(|tenant| tenant.env.eq(&env));
+
+            let outcome = update_all_tenants_cors_origins(
+                storage,
+                storage_client,
+                &tenants,
+                skip_failed,
+                CorsOriginsChange::Add(origin),
+                force,
+            )
+            .await;
+
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, Some(tenants)).await?;
+            check_failed_tenants(failed)
+        }
+
+        Commands::RemoveStorageCorsOrigin {
+            env,
+            tenant_id: None,
+            skip_failed,
+            origin,
+            force,
+            table,
+            ..
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+            tenants.retain(|tenant| tenant.env.eq(&env));
+
+            let outcome = update_all_tenants_cors_origins(
+                storage,
+                storage_client,
+                &tenants,
+                skip_failed,
+                CorsOriginsChange::Remove(origin),
+                force,
+            )
+            .await;
+
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, Some(tenants)).await?;
+            check_failed_tenants(failed)
+        }
+
+        Commands::SetAllowedStorageCorsOrigins {
+            env,
+            tenant_id: Some(tenant_id),
             origin,
+            ..
         } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+
             let tenant =
-                docbox_management::tenant::get_tenant::get_tenant(&db_provider, &env, tenant_id)
+                docbox_management::tenant::get_tenant::get_tenant(db_provider, &env, tenant_id)
                     .await?
-                    .context("tenant not found")?;
+                    .context(ErrorCode::TenantNotFound)?;
 
             let storage = storage.create_layer(tenant.storage_layer_options());
 
             storage.set_bucket_cors_origins(origin).await?;
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     println!("updated tenant allowed origins")
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
                             "success": true
-                        }))?
-                    );
+                        }),
+                    )?;
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::GetStorageCors { env, tenant_id } => {
+            let db_provider = backends.db_provider().await?;
+            let storage_client = backends.storage_client().await;
+
+            let tenant = get_tenant(db_provider, &env, tenant_id)
+                .await?
+                .context(ErrorCode::TenantNotFound)?;
+
+            let origins = storage_client
+                .get_bucket_cors_origins(&tenant.s3_name)
+                .await?
+                .context("storage backend does not support reading the bucket CORS origins")?;
+
+            match output.format {
+                OutputFormat::Human => {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Allowed Origin"]);
+
+                    for origin in origins {
+                        table.add_row(vec![Cell::new(origin)]);
+                    }
+
+                    println!("{table}")
+                }
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
+                            "origins": origins
+                        }),
+                    )?;
                 }
             }
 
             Ok(())
         }
 
-        Commands::MigrateTenantIam { env, tenant_id } => {
+        Commands::AddStorageCorsOrigin {
+            env,
+            tenant_id: Some(tenant_id),
+            origin,
+            force,
+            ..
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let tenant = get_tenant(db_provider, &env, tenant_id)
+                .await?
+                .context(ErrorCode::TenantNotFound)?;
+
+            let storage = storage.create_layer(tenant.storage_layer_options());
+            let diff = CorsOriginsChange::Add(origin)
+                .apply_to_bucket(&storage, storage_client, force)
+                .await?;
+
+            print_cors_origins_diff(output, &diff)
+        }
+
+        Commands::RemoveStorageCorsOrigin {
+            env,
+            tenant_id: Some(tenant_id),
+            origin,
+            force,
+            ..
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let tenant = get_tenant(db_provider, &env, tenant_id)
+                .await?
+                .context(ErrorCode::TenantNotFound)?;
+
+            let storage = storage.create_layer(tenant.storage_layer_options());
+            let diff = CorsOriginsChange::Remove(origin)
+                .apply_to_bucket(&storage, storage_client, force)
+                .await?;
+
+            print_cors_origins_diff(output, &diff)
+        }
+
+        Commands::StorageUsage {
+            env,
+            tenant_id,
+            largest,
+            skip_failed,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage_client = backends.storage_client().await;
+
             let mut tenants =
-                docbox_management::tenant::get_tenants::get_tenants(&db_provider).await?;
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
 
             tenants.retain(|tenant| {
-                tenant.env.eq(&env) && tenant_id.is_none_or(|id| tenant.id.eq(&id))
+                env.as_ref().is_none_or(|env| tenant.env.eq(env))
+                    && tenant_id.is_none_or(|id| tenant.id.eq(&id))
             });
 
-            let mut migrated_tenants = Vec::new();
-
-            for mut tenant in tenants {
-                if tenant.db_iam_user_name.is_some() {
-                    tracing::debug!(?tenant, "skipping tenant with iam user name already set");
-                    continue;
-                }
+            if tenant_id.is_some() && tenants.is_empty() {
+                return Err(ErrorCode::TenantNotFound.into());
+            }
 
-                migrate_tenant_secret_to_iam(&db_provider, &secrets, &mut tenant).await?;
-                migrated_tenants.push(tenant);
+            let mut reports = Vec::new();
+
+            for tenant in tenants {
+                let result = storage_client
+                    .get_bucket_usage(&tenant.s3_name, largest)
+                    .await;
+
+                let (usage, error) = match result {
+                    Ok(usage) => (usage, None),
+                    Err(error) => {
+                        if !skip_failed {
+                            return Err(error.wrap_err(format!(
+                                "failed to get storage usage for tenant {}",
+                                tenant.id
+                            )));
+                        }
+
+                        tracing::error!(?error, "failed to get tenant storage usage");
+                        (BucketUsage::default(), Some(format!("{error:#}")))
+                    }
+                };
+
+                reports.push(TenantStorageUsage {
+                    tenant_id: tenant.id,
+                    name: tenant.name,
+                    env: tenant.env,
+                    bucket: tenant.s3_name,
+                    usage,
+                    error,
+                });
             }
 
-            match args.format {
+            match output.format {
                 OutputFormat::Human => {
                     let mut table = Table::new();
                     table
                         .load_preset(UTF8_FULL)
                         .apply_modifier(UTF8_ROUND_CORNERS)
                         .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-                        .set_header(vec!["ID", "Name", "Env", "Outcome"]);
+                        .set_header(vec!["ID", "Name", "Env", "Bucket", "Objects", "Total Size"]);
+
+                    let mut largest_table = Table::new();
+                    largest_table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Tenant", "Key", "Size"]);
+
+                    for report in &reports {
+                        let (objects, total_size) = match report.error.as_ref() {
+                            Some(error) => (format!("Failed: {error}"), String::new()),
+                            None => (
+                                report.usage.object_count.to_string(),
+                                format_bytes(report.usage.total_bytes),
+                            ),
+                        };
 
-                    for tenant in migrated_tenants {
                         table.add_row(vec![
-                            Cell::new(tenant.id.to_string()),
-                            Cell::new(tenant.name),
-                            Cell::new(tenant.env),
-                            Cell::new("Success"),
+                            Cell::new(report.tenant_id.to_string()),
+                            Cell::new(&report.name),
+                            Cell::new(&report.env),
+                            Cell::new(&report.bucket),
+                            Cell::new(objects),
+                            Cell::new(total_size),
                         ]);
+
+                        for object in &report.usage.largest_objects {
+                            largest_table.add_row(vec![
+                                Cell::new(&report.name),
+                                Cell::new(&object.key),
+                                Cell::new(format_bytes(object.size)),
+                            ]);
+                        }
                     }
 
-                    println!("migrated tenants to IAM based authentication");
-                    println!("{table}")
+                    println!("{table}");
+
+                    if largest > 0 {
+                        println!("largest objects");
+                        println!("{largest_table}");
+                    }
                 }
-                OutputFormat::Json => {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&json!({
-                            "migrated_tenants": migrated_tenants,
-                            "success": true
-                        }))?
-                    );
+                _ => {
+                    let rows: Vec<_> = reports
+                        .iter()
+                        .map(|report| {
+                            let largest_objects = report
+                                .usage
+                                .largest_objects
+                                .iter()
+                                .map(|object| format!("{}={}", object.key, object.size))
+                                .collect::<Vec<_>>()
+                                .join(";");
+
+                            json!({
+                                "tenant_id": report.tenant_id,
+                                "name": report.name,
+                                "env": report.env,
+                                "bucket": report.bucket,
+                                "object_count": report.usage.object_count,
+                                "total_bytes": report.usage.total_bytes,
+                                "largest_objects": largest_objects,
+                                "error": report.error,
+                            })
+                        })
+                        .collect();
+
+                    print_output_rows(output, &reports, &rows)?;
                 }
             }
 
             Ok(())
         }
-    }
+
+        Commands::MoveTenantStorage {
+            env,
+            tenant_id,
+            to_bucket,
+            from_bucket,
+            delete_old_bucket,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let mut tenant = get_tenant(db_provider, &env, tenant_id)
+                .await?
+                .context(ErrorCode::TenantNotFound)?;
+
+            // The tenant is already using the new bucket when resuming a move
+            // that was interrupted after the tenant was updated
+            let tenant_updated = tenant.s3_name == to_bucket;
+
+            let from_bucket = match from_bucket {
+                Some(from_bucket) if from_bucket == to_bucket => {
+                    eyre::bail!("cannot move tenant storage to the bucket it is moving from");
+                }
+                Some(from_bucket) if !tenant_updated && from_bucket != tenant.s3_name => {
+                    eyre::bail!(
+                        "tenant storage is using the bucket {}, not {from_bucket}",
+                        tenant.s3_name
+                    );
+                }
+                Some(from_bucket) => from_bucket,
+                None if tenant_updated => eyre::bail!(
+                    "tenant storage is already using the bucket {to_bucket}, use --from-bucket to resume a previous move"
+                ),
+                None => tenant.s3_name.clone(),
+            };
+
+            let old_storage = storage.create_layer(StorageLayerOptions {
+                bucket_name: from_bucket.clone(),
+            });
+            let new_storage = storage.create_layer(StorageLayerOptions {
+                bucket_name: to_bucket.clone(),
+            });
+
+            // An existing bucket is expected when resuming a previous move
+            let bucket_outcome = new_storage
+                .create_bucket()
+                .await
+                .context("failed to create bucket")?;
+
+            if bucket_outcome == CreateBucketOutcome::Existing {
+                tracing::info!(%to_bucket, "bucket already exists, resuming move");
+            }
+
+            // Copy the bucket configuration
+            match storage_client.get_bucket_cors_origins(&from_bucket).await? {
+                Some(origins) if !origins.is_empty() => {
+                    new_storage
+                        .set_bucket_cors_origins(origins)
+                        .await
+                        .context("failed to set bucket cors")?;
+                }
+                Some(_) => {}
+                None => {
+                    tracing::warn!("unable to read the bucket cors origins, skipping copying them");
+                }
+            }
+
+            storage_client
+                .copy_bucket_notifications(&from_bucket, &to_bucket)
+                .await?;
+
+            for migration_name in new_storage.get_pending_migrations(Vec::new()).await? {
+                new_storage
+                    .apply_migration(&migration_name)
+                    .await
+                    .with_context(|| {
+                        format!("failed to apply storage migration {migration_name}")
+                    })?;
+            }
+
+            // Objects are replaced when resuming before the tenant was updated, afterwards
+            // the new bucket is in use and only missing objects are copied
+            let mut outcome = storage_client
+                .copy_bucket_objects(&from_bucket, &to_bucket, tenant_updated)
+                .await?;
+
+            tracing::info!(?outcome, "copied tenant storage objects");
+
+            if !tenant_updated {
+                // Update the tenant to use the new bucket
+                {
+                    let root_db = db_provider
+                        .connect(ROOT_DATABASE_NAME)
+                        .await
+                        .context("failed to connect to root db")?;
+
+                    let _guard = close_pool_on_drop(&root_db);
+
+                    tenant
+                        .update(
+                            &root_db,
+                            UpdateTenant {
+                                s3_name: Some(to_bucket.clone()),
+                                ..Default::default()
+                            },
+                        )
+                        .await
+                        .context("failed to update tenant")?;
+                }
+
+                // Tell the API server to flush its cached tenants
+                flush_tenant_cache(&config.api)
+                    .await
+                    .context("failed to flush tenant cache")?;
+
+                // Copy objects created in the old bucket while the objects were
+                // being copied, the new bucket is now in use so existing objects
+                // are not replaced
+                let late_outcome = storage_client
+                    .copy_bucket_objects(&from_bucket, &to_bucket, true)
+                    .await?;
+
+                tracing::info!(?late_outcome, "copied objects created during the move");
+
+                outcome.copied_objects += late_outcome.copied_objects;
+                outcome.copied_bytes += late_outcome.copied_bytes;
+            }
+
+            if delete_old_bucket {
+                for object in storage_client.list_objects(&from_bucket).await? {
+                    old_storage
+                        .delete_file(&object.key)
+                        .await
+                        .with_context(|| format!("failed to delete object {}", object.key))?;
+                }
+
+                old_storage
+                    .delete_bucket()
+                    .await
+                    .context("failed to delete old bucket")?;
+            }
+
+            match output.format {
+                OutputFormat::Human => {
+                    println!("moved tenant storage from {from_bucket} to {to_bucket}");
+
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+
+                    table.add_row(vec![
+                        Cell::new("Copied Objects"),
+                        Cell::new(outcome.copied_objects),
+                    ]);
+                    table.add_row(vec![
+                        Cell::new("Copied Size"),
+                        Cell::new(format_bytes(outcome.copied_bytes)),
+                    ]);
+                    table.add_row(vec![
+                        Cell::new("Skipped Objects"),
+                        Cell::new(outcome.skipped_objects),
+                    ]);
+                    table.add_row(vec![
+                        Cell::new("Deleted Old Bucket"),
+                        Cell::new(delete_old_bucket),
+                    ]);
+
+                    println!("{table}");
+                }
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
+                            "from_bucket": from_bucket,
+                            "to_bucket": to_bucket,
+                            "copied_objects": outcome.copied_objects,
+                            "copied_bytes": outcome.copied_bytes,
+                            "skipped_objects": outcome.skipped_objects,
+                            "deleted_old_bucket": delete_old_bucket,
+                        }),
+                    )?;
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::FindOrphans {
+            kind,
+            prefix,
+            delete,
+            yes,
+            permanently_delete_secret,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+            let storage = backends.storage().await;
+            let storage_client = backends.storage_client().await;
+
+            let kinds = if kind.is_empty() {
+                ResourceKind::value_variants().to_vec()
+            } else {
+                kind
+            };
+
+            let search_client = backends.search_client().await?;
+            let secrets_client = SecretsClient::from_config(aws_config, &config.secrets);
+
+            let tenants = docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+
+            let orphans = find_orphans(
+                db_provider,
+                storage_client,
+                search_client,
+                &secrets_client,
+                config,
+                source.aws_config_secret.as_deref(),
+                &tenants,
+                &kinds,
+                &prefix,
+            )
+            .await?;
+
+            let mut outcomes: Vec<Option<Result<(), String>>> =
+                orphans.iter().map(|_| None).collect();
+
+            if delete {
+                for (orphan, outcome) in orphans.iter().zip(outcomes.iter_mut()) {
+                    if !yes && !confirm_delete_orphan(orphan)? {
+                        continue;
+                    }
+
+                    let result = delete_orphan(
+                        db_provider,
+                        storage,
+                        storage_client,
+                        search_client,
+                        secrets,
+                        orphan,
+                        permanently_delete_secret,
+                    )
+                    .await;
+
+                    *outcome = Some(result.map_err(|error| {
+                        tracing::error!(?error, name = %orphan.name, "failed to delete orphaned resource");
+                        format!("{error:#}")
+                    }));
+                }
+            }
+
+            match output.format {
+                OutputFormat::Human => {
+                    if orphans.is_empty() {
+                        println!("no orphaned resources found");
+                        return Ok(());
+                    }
+
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Kind", "Name", "Outcome"]);
+
+                    for (orphan, outcome) in orphans.iter().zip(outcomes) {
+                        table.add_row(vec![
+                            Cell::new(orphan.kind),
+                            Cell::new(&orphan.name),
+                            Cell::new(match outcome {
+                                None => "Orphaned".to_string(),
+                                Some(Ok(())) => "Deleted".to_string(),
+                                Some(Err(error)) => format!("Failed: {error}"),
+                            }),
+                        ]);
+                    }
+
+                    println!("{table}")
+                }
+                _ => {
+                    let orphans: Vec<_> = orphans
+                        .iter()
+                        .zip(outcomes)
+                        .map(|(orphan, outcome)| {
+                            json!({
+                                "kind": orphan.kind,
+                                "name": orphan.name,
+                                "deleted": matches!(outcome, Some(Ok(()))),
+                                "error": outcome.and_then(Result::err),
+                            })
+                        })
+                        .collect();
+
+                    print_output_rows(
+                        output,
+                        &json!({
+                            "orphans": orphans
+                        }),
+                        &orphans,
+                    )?;
+                }
+            }
+
+            Ok(())
+        }
+
+        Commands::MigrateTenantSecret {
+            env,
+            tenant_id,
+            secret_name,
+            skip_failed,
+            table,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+            let secrets_client = SecretsClient::from_config(aws_config, &config.secrets);
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+
+            tenants.retain(|tenant| {
+                tenant.env.eq(&env) && tenant_id.is_none_or(|id| tenant.id.eq(&id))
+            });
+
+            if tenant_id.is_some() && tenants.is_empty() {
+                return Err(ErrorCode::TenantNotFound.into());
+            }
+
+            let mut migrated_tenants = Vec::new();
+            let mut skipped_tenants = Vec::new();
+            let mut failed_tenants = Vec::new();
+
+            for mut tenant in tenants {
+                if tenant.db_iam_user_name.is_none() {
+                    tracing::debug!(?tenant, "skipping tenant without an iam user name");
+                    skipped_tenants.push(tenant);
+                    continue;
+                }
+
+                let secret_name = secret_name
+                    .replace("{env}", &tenant.env)
+                    .replace("{tenant_id}", &tenant.id.to_string())
+                    .replace("{db_name}", &tenant.db_name);
+
+                match migrate_tenant_iam_to_secret(
+                    db_provider,
+                    secrets,
+                    &secrets_client,
+                    &config.database,
+                    &mut tenant,
+                    &secret_name,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        migrated_tenants.push(tenant);
+                    }
+                    Err(error) => {
+                        tracing::error!(?error, "failed to migrate tenant to secret");
+                        failed_tenants.push(FailedTenant {
+                            error: format!("{error:#}"),
+                            tenant,
+                        });
+
+                        if !skip_failed {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if !migrated_tenants.is_empty() {
+                // Tell the API server to drop its cached tenants and connections
+                flush_tenant_cache(&config.api)
+                    .await
+                    .context("failed to flush tenant cache")?;
+            }
+
+            if let OutputFormat::Human = output.format {
+                println!(
+                    "migrated {} tenants to secret based authentication ({} skipped, {} failed)",
+                    migrated_tenants.len(),
+                    skipped_tenants.len(),
+                    failed_tenants.len()
+                );
+            }
+
+            let rows: Vec<TenantOutcomeRow> = migrated_tenants
+                .iter()
+                .map(|tenant| TenantOutcomeRow::success(tenant.clone()))
+                .chain(skipped_tenants.iter().map(|tenant| {
+                    TenantOutcomeRow::skipped(tenant.clone(), "already using secrets")
+                }))
+                .chain(failed_tenants.iter().map(|failed| {
+                    TenantOutcomeRow::failed(failed.tenant.clone(), failed.error.clone())
+                }))
+                .collect();
+
+            print_tenant_outcome_rows(
+                output,
+                &table,
+                &json!({
+                    "migrated_tenants": migrated_tenants,
+                    "skipped_tenants": skipped_tenants,
+                    "failed_tenants": failed_tenants,
+                    "success": failed_tenants.is_empty()
+                }),
+                rows,
+            )?;
+
+            check_failed_tenants(failed_tenants.len())
+        }
+
+        Commands::RotateTenantDbSecret {
+            env,
+            tenant_id,
+            skip_failed,
+            table,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+
+            tenants.retain(|tenant| {
+                tenant.env.eq(&env) && tenant_id.is_none_or(|id| tenant.id.eq(&id))
+            });
+
+            if tenant_id.is_some() && tenants.is_empty() {
+                return Err(ErrorCode::TenantNotFound.into());
+            }
+
+            let mut outcome = MigrateTenantsOutcome::default();
+
+            for tenant in &tenants {
+                // Only a specifically requested tenant is reported when using IAM
+                if tenant_id.is_none() && tenant.db_secret_name.is_none() {
+                    tracing::debug!(?tenant, "skipping tenant without a database secret");
+                    continue;
+                }
+
+                let result =
+                    rotate_tenant_db_secret(db_provider, secrets, &config.database, tenant).await;
+
+                let target = TenantTarget {
+                    env: tenant.env.clone(),
+                    name: tenant.name.clone(),
+                    tenant_id: tenant.id,
+                };
+
+                match result {
+                    Ok(_) => {
+                        outcome.applied_tenants.push(target);
+                    }
+                    Err(error) => {
+                        tracing::error!(?error, "failed to rotate tenant database secret");
+                        outcome.failed_tenants.push((format!("{error:#}"), target));
+
+                        if !skip_failed {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if !outcome.applied_tenants.is_empty() {
+                // Tell the API server to drop connections using the old credentials
+                flush_tenant_cache(&config.api)
+                    .await
+                    .context("failed to flush tenant cache")?;
+            }
+
+            let failed = outcome.failed_tenants.len();
+            print_tenants_outcome(output, &table, db_provider, outcome, Some(tenants)).await?;
+            check_failed_tenants(failed)
+        }
+
+        Commands::MigrateTenantIam {
+            env,
+            tenant_id,
+            skip_failed,
+            table,
+        } => {
+            let db_provider = backends.db_provider().await?;
+            let secrets = backends.secrets().await;
+
+            let mut tenants =
+                docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+
+            tenants.retain(|tenant| {
+                tenant.env.eq(&env) && tenant_id.is_none_or(|id| tenant.id.eq(&id))
+            });
+
+            if tenant_id.is_some() && tenants.is_empty() {
+                return Err(ErrorCode::TenantNotFound.into());
+            }
+
+            let mut migrated_tenants = Vec::new();
+            let mut skipped_tenants = Vec::new();
+            let mut failed_tenants = Vec::new();
+
+            for mut tenant in tenants {
+                if tenant.db_iam_user_name.is_some() {
+                    tracing::debug!(?tenant, "skipping tenant with iam user name already set");
+                    skipped_tenants.push(tenant);
+                    continue;
+                }
+
+                match migrate_tenant_secret_to_iam(db_provider, secrets, &mut tenant).await {
+                    Ok(_) => {
+                        migrated_tenants.push(tenant);
+                    }
+                    Err(error) => {
+                        tracing::error!(?error, "failed to migrate tenant to iam");
+                        failed_tenants.push(FailedTenant {
+                            error: format!("{error:#}"),
+                            tenant,
+                        });
+
+                        if !skip_failed {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if let OutputFormat::Human = output.format {
+                println!(
+                    "migrated {} tenants to IAM based authentication ({} skipped, {} failed)",
+                    migrated_tenants.len(),
+                    skipped_tenants.len(),
+                    failed_tenants.len()
+                );
+            }
+
+            let rows: Vec<TenantOutcomeRow> =
+                migrated_tenants
+                    .iter()
+                    .map(|tenant| TenantOutcomeRow::success(tenant.clone()))
+                    .chain(skipped_tenants.iter().map(|tenant| {
+                        TenantOutcomeRow::skipped(tenant.clone(), "already using IAM")
+                    }))
+                    .chain(failed_tenants.iter().map(|failed| {
+                        TenantOutcomeRow::failed(failed.tenant.clone(), failed.error.clone())
+                    }))
+                    .collect();
+
+            print_tenant_outcome_rows(
+                output,
+                &table,
+                &json!({
+                    "migrated_tenants": migrated_tenants,
+                    "skipped_tenants": skipped_tenants,
+                    "failed_tenants": failed_tenants,
+                    "success": failed_tenants.is_empty()
+                }),
+                rows,
+            )?;
+
+            check_failed_tenants(failed_tenants.len())
+        }
+    }
+}
+
+/// Fail with [ErrorCode::PartialFailure] when `failed` tenants is non zero,
+/// checked by batch commands after printing their outcome
+fn check_failed_tenants(failed: usize) -> eyre::Result<()> {
+    if failed > 0 {
+        return Err(eyre::Report::new(ErrorCode::PartialFailure)
+            .wrap_err(format!("operation failed for {failed} tenants")));
+    }
+
+    Ok(())
+}
+
+/// Tenant that failed to migrate along with the error
+#[derive(Serialize)]
+struct FailedTenant {
+    error: String,
+    tenant: Tenant,
+}
+
+/// Tenant an outcome applies to
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OutcomeTenant {
+    /// Tenant with all of its details
+    Tenant(Tenant),
+    /// Identity of a tenant whose details were not loaded
+    Target {
+        id: TenantId,
+        name: String,
+        env: String,
+    },
+}
+
+impl From<Tenant> for OutcomeTenant {
+    fn from(tenant: Tenant) -> Self {
+        OutcomeTenant::Tenant(tenant)
+    }
+}
+
+impl From<&TenantTarget> for OutcomeTenant {
+    fn from(target: &TenantTarget) -> Self {
+        OutcomeTenant::Target {
+            id: target.tenant_id,
+            name: target.name.clone(),
+            env: target.env.clone(),
+        }
+    }
+}
+
+impl TenantRow for OutcomeTenant {
+    fn column_value(&self, column: TenantColumn) -> Option<String> {
+        match self {
+            OutcomeTenant::Tenant(tenant) => tenant.column_value(column),
+            OutcomeTenant::Target { id, name, env } => match column {
+                TenantColumn::Id => Some(id.to_string()),
+                TenantColumn::Name => Some(name.clone()),
+                TenantColumn::Env => Some(env.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Outcome of an operation for a single tenant
+#[derive(Serialize)]
+struct TenantOutcomeRow {
+    #[serde(flatten)]
+    tenant: OutcomeTenant,
+    outcome: &'static str,
+    error: Option<String>,
+    /// Reason the tenant was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'static str>,
+}
+
+impl TenantOutcomeRow {
+    fn success(tenant: impl Into<OutcomeTenant>) -> Self {
+        Self {
+            tenant: tenant.into(),
+            outcome: "success",
+            error: None,
+            reason: None,
+        }
+    }
+
+    fn skipped(tenant: impl Into<OutcomeTenant>, reason: &'static str) -> Self {
+        Self {
+            tenant: tenant.into(),
+            outcome: "skipped",
+            error: None,
+            reason: Some(reason),
+        }
+    }
+
+    fn failed(tenant: impl Into<OutcomeTenant>, error: String) -> Self {
+        Self {
+            tenant: tenant.into(),
+            outcome: "failed",
+            error: Some(error),
+            reason: None,
+        }
+    }
+
+    /// Outcome text for human readable tables
+    fn display_outcome(&self) -> String {
+        match (self.outcome, &self.error, self.reason) {
+            (_, Some(error), _) => format!("Failed: {error}"),
+            ("skipped", _, Some(reason)) => format!("Skipped: {reason}"),
+            ("skipped", _, None) => "Skipped".to_string(),
+            _ => "Success".to_string(),
+        }
+    }
+}
+
+async fn run_context_command(output: &OutputArgs, command: ContextCommand) -> eyre::Result<()> {
+    let mut contexts = ContextsFile::load().await?;
+
+    match command {
+        ContextCommand::Use { name } => {
+            contexts.get(&name)?;
+            contexts.current = Some(name.clone());
+            contexts.save().await?;
+
+            match output.format {
+                OutputFormat::Human => {
+                    println!("switched to context {name}");
+                }
+                _ => {
+                    print_output(
+                        output,
+                        &json!({
+                            "current": name
+                        }),
+                    )?;
+                }
+            }
+        }
+
+        ContextCommand::List => match output.format {
+            OutputFormat::Human => {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                    .set_header(vec!["Current", "Name", "Source"]);
+
+                for (name, context) in &contexts.contexts {
+                    let current = contexts
+                        .current
+                        .as_ref()
+                        .is_some_and(|current| current.eq(name));
+
+                    table.add_row(vec![
+                        Cell::new(if current { "*" } else { "" }),
+                        Cell::new(name),
+                        Cell::new(context),
+                    ]);
+                }
+
+                println!("{table}");
+            }
+            _ => {
+                let rows: Vec<_> = contexts
+                    .contexts
+                    .iter()
+                    .map(|(name, context)| {
+                        json!({
+                            "name": name,
+                            "current": contexts.current.as_ref().is_some_and(|current| current.eq(name)),
+                            "config": context.config,
+                            "aws_config_secret": context.aws_config_secret,
+                        })
+                    })
+                    .collect();
+
+                print_output_rows(output, &contexts, &rows)?;
+            }
+        },
+
+        ContextCommand::Current => match output.format {
+            OutputFormat::Human => match &contexts.current {
+                Some(current) => println!("{current}"),
+                None => println!("no current context"),
+            },
+            _ => {
+                print_output(
+                    output,
+                    &json!({
+                        "current": contexts.current
+                    }),
+                )?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_config_command(
+    aws_config: &SdkConfig,
+    source: ConfigSource,
+    output: &OutputArgs,
+    command: ConfigCommand,
+) -> eyre::Result<()> {
+    match command {
+        ConfigCommand::Validate => {
+            let config = load_config(aws_config, source).await?;
+            let checks = validate_config(aws_config, &config).await;
+            let valid = checks.iter().all(|check| check.error.is_none());
+
+            match output.format {
+                OutputFormat::Human => {
+                    let mut table = Table::new();
+                    table
+                        .load_preset(UTF8_FULL)
+                        .apply_modifier(UTF8_ROUND_CORNERS)
+                        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                        .set_header(vec!["Backend", "Outcome"]);
+
+                    for check in &checks {
+                        table.add_row(vec![
+                            Cell::new(check.backend),
+                            Cell::new(match &check.error {
+                                Some(error) => format!("Failed: {error}"),
+                                None => "Success".to_string(),
+                            }),
+                        ]);
+                    }
+
+                    println!("{table}");
+                }
+                // Failed checks are reported through the error output
+                _ if valid => {
+                    print_output(
+                        output,
+                        &json!({
+                            "valid": valid,
+                            "checks": checks
+                        }),
+                    )?;
+                }
+                _ => {}
+            }
+
+            if !valid {
+                let failed = checks
+                    .iter()
+                    .filter_map(|check| {
+                        Some(format!("{}: {}", check.backend, check.error.as_ref()?))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                return Err(eyre::Report::new(ErrorCode::BackendUnreachable)
+                    .wrap_err(format!("config validation failed ({failed})")));
+            }
+        }
+
+        ConfigCommand::Show => {
+            let config = load_config(aws_config, source).await?;
+            let config = redact_config(&config)?;
+            print_output(output, &config)?;
+        }
+
+        ConfigCommand::Init { .. } => {
+            unreachable!("config init is handled before resolving the config source")
+        }
+    }
+
+    Ok(())
+}
+
+/// Interactively create a config file at `path`
+async fn run_config_init(output: &OutputArgs, path: PathBuf, force: bool) -> eyre::Result<()> {
+    if !force && tokio::fs::try_exists(&path).await? {
+        eyre::bail!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        );
+    }
+
+    let config = init_config()?;
+    tokio::fs::write(&path, serde_json::to_string_pretty(&config)?)
+        .await
+        .context("failed to write config")?;
+
+    match output.format {
+        OutputFormat::Human => {
+            println!("created config at {}", path.display());
+        }
+        _ => {
+            print_output(
+                output,
+                &json!({
+                    "path": path
+                }),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask the user to confirm the deletion of the `orphan` resource, closing
+/// the input cancels the remaining deletions
+fn confirm_delete_orphan(orphan: &OrphanedResource) -> eyre::Result<bool> {
+    use std::io::Write;
+
+    eprint!(
+        "permanently delete {} \"{}\"? type \"delete\" to confirm: ",
+        orphan.kind, orphan.name
+    );
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut input)
+        .context("failed to read confirmation")?;
+
+    if read == 0 {
+        return Err(ErrorCode::Cancelled.into());
+    }
+
+    Ok(input.trim() == "delete")
+}
+
+/// Storage usage report for a tenant
+#[derive(Serialize)]
+struct TenantStorageUsage {
+    tenant_id: TenantId,
+    name: String,
+    env: String,
+    bucket: String,
+    #[serde(flatten)]
+    usage: BucketUsage,
+    error: Option<String>,
+}
+
+/// Apply a CORS origins change to the storage bucket of each of the `tenants`
+async fn update_all_tenants_cors_origins(
+    storage: &StorageLayerFactory,
+    storage_client: &StorageClient,
+    tenants: &[Tenant],
+    skip_failed: bool,
+    change: CorsOriginsChange,
+    force: bool,
+) -> MigrateTenantsOutcome {
+    let mut outcome = MigrateTenantsOutcome::default();
+
+    for tenant in tenants {
+        let storage = storage.create_layer(tenant.storage_layer_options());
+        let result = change
+            .apply_to_bucket(&storage, storage_client, force)
+            .await;
+
+        let target = TenantTarget {
+            env: tenant.env.clone(),
+            name: tenant.name.clone(),
+            tenant_id: tenant.id,
+        };
+
+        match result {
+            Ok(_) => {
+                outcome.applied_tenants.push(target);
+            }
+            Err(error) => {
+                tracing::error!(?error, "failed to update tenant allowed origins");
+                outcome.failed_tenants.push((format!("{error:#}"), target));
+
+                if !skip_failed {
+                    break;
+                }
+            }
+        }
+    }
+
+    outcome
+}
+
+/// Print the details of a tenant in the human readable format
+fn print_tenant_detail(detail: &TenantDetail) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+
+    for (label, value) in detail.rows() {
+        table.add_row(vec![Cell::new(label), Cell::new(value)]);
+    }
+
+    println!("{table}");
+}
+
+fn print_cors_origins_diff(output: &OutputArgs, diff: &CorsOriginsDiff) -> eyre::Result<()> {
+    match output.format {
+        OutputFormat::Human => {
+            if diff.is_changed() {
+                println!("updated tenant allowed origins");
+            } else {
+                println!("tenant allowed origins unchanged");
+            }
+
+            diff.print();
+        }
+        _ => {
+            print_output(output, diff)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the outcome of an operation for each tenant, `tenants` are the tenants
+/// already loaded by the operation. Outcomes only contain the tenant identity
+/// so when the tenants were not loaded they are only loaded for tables that show
+/// other tenant columns
+async fn print_tenants_outcome(
+    output: &OutputArgs,
+    table: &TableArgs,
+    db_provider: &impl DatabaseProvider,
+    outcome: MigrateTenantsOutcome,
+    tenants: Option<Vec<Tenant>>,
+) -> eyre::Result<()> {
+    let tenants = match tenants {
+        Some(tenants) => tenants,
+        None if table.needs_details()
+            && matches!(output.format, OutputFormat::Human | OutputFormat::Csv) =>
+        {
+            match docbox_management::tenant::get_tenants::get_tenants(db_provider).await {
+                Ok(tenants) => tenants,
+                Err(error) => {
+                    // The operation has already completed, only the extra columns are missing
+                    tracing::warn!(?error, "failed to load tenant details for the outcome");
+                    Vec::new()
+                }
+            }
+        }
+        None => Vec::new(),
+    };
+
+    let find_tenant = |target: &TenantTarget| {
+        tenants
+            .iter()
+            .find(|tenant| tenant.id.eq(&target.tenant_id) && tenant.env.eq(&target.env))
+            .cloned()
+            .map(OutcomeTenant::Tenant)
+            .unwrap_or_else(|| OutcomeTenant::from(target))
+    };
+
+    let rows: Vec<TenantOutcomeRow> =
+        outcome
+            .applied_tenants
+            .iter()
+            .map(|target| TenantOutcomeRow::success(find_tenant(target)))
+            .chain(outcome.failed_tenants.iter().map(|(error, target)| {
+                TenantOutcomeRow::failed(find_tenant(target), error.clone())
+            }))
+            .collect();
+
+    print_tenant_outcome_rows(output, table, &outcome, rows)
+}
+
+/// Print the outcome of an operation for each tenant, `value` is the
+/// full outcome used for the JSON and YAML formats
+fn print_tenant_outcome_rows<T: Serialize>(
+    output: &OutputArgs,
+    table: &TableArgs,
+    value: &T,
+    mut rows: Vec<TenantOutcomeRow>,
+) -> eyre::Result<()> {
+    table.sort(&mut rows, |row| &row.tenant);
+
+    match output.format {
+        OutputFormat::Human => {
+            let mut headers = table.headers();
+            headers.push("Outcome");
+
+            let mut output = Table::new();
+            output
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                .set_header(headers);
+
+            for row in &rows {
+                let mut cells = table.cells(&row.tenant);
+                cells.push(Cell::new(row.display_outcome()));
+                output.add_row(cells);
+            }
+
+            println!("{output}")
+        }
+        OutputFormat::Csv => {
+            let rows: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    let mut csv_row = table.row(&row.tenant);
+                    csv_row.insert("outcome".to_string(), row.outcome.into());
+                    csv_row.insert("error".to_string(), row.error.clone().into());
+                    csv_row
+                })
+                .collect();
+
+            print_output(output, &rows)?;
+        }
+        _ => {
+            print_output_rows(output, value, &rows)?;
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,246 @@
+//! Finding resources within the deployment that are not owned by any tenant
+
+use crate::{
+    search::SearchClient,
+    secrets::SecretsClient,
+    storage::StorageClient,
+    tenants::{TenantFilter, TenantResource, find_tenants},
+};
+use clap::ValueEnum;
+use docbox_management::{
+    config::ServerConfigData,
+    core::{
+        search::SearchIndexFactoryConfig,
+        secrets::SecretManager,
+        storage::{StorageLayerFactory, StorageLayerOptions},
+    },
+    database::{
+        DatabaseProvider, ROOT_DATABASE_NAME, close_pool_on_drop, create::delete_database,
+        models::tenant::Tenant, sqlx,
+    },
+};
+use eyre::Context;
+use serde::Serialize;
+use std::{collections::HashSet, fmt::Display};
+
+/// Databases that belong to the database server rather than docbox
+const SYSTEM_DATABASES: &[&str] = &["postgres", "rdsadmin"];
+
+/// Kind of resource that can be orphaned
+#[derive(ValueEnum, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    /// Tenant databases
+    Database,
+    /// Tenant storage buckets
+    Bucket,
+    /// Tenant search indexes
+    SearchIndex,
+    /// Tenant database secrets
+    Secret,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResourceKind::Database => "Database",
+            ResourceKind::Bucket => "Bucket",
+            ResourceKind::SearchIndex => "Search Index",
+            ResourceKind::Secret => "Secret",
+        })
+    }
+}
+
+/// Resource that is not owned by any tenant
+#[derive(Serialize)]
+pub struct OrphanedResource {
+    pub kind: ResourceKind,
+    pub name: String,
+}
+
+/// Find every resource of the requested `kinds` starting with `prefix` that is
+/// not referenced by one of the `tenants` or by the deployment itself,
+/// `config_secret` is the secret the server config was loaded from
+#[allow(clippy::too_many_arguments)]
+pub async fn find_orphans(
+    db_provider: &impl DatabaseProvider,
+    storage_client: &StorageClient,
+    search_client: &SearchClient,
+    secrets_client: &SecretsClient,
+    config: &ServerConfigData,
+    config_secret: Option<&str>,
+    tenants: &[Tenant],
+    kinds: &[ResourceKind],
+    prefix: &str,
+) -> eyre::Result<Vec<OrphanedResource>> {
+    let mut orphans = Vec::new();
+
+    for kind in kinds {
+        let names = match kind {
+            ResourceKind::Database => list_databases(db_provider).await?,
+            ResourceKind::Bucket => storage_client.list_buckets().await?,
+            ResourceKind::SearchIndex => match search_client.list_indexes().await? {
+                Some(names) => names,
+                None => {
+                    tracing::info!(
+                        "skipping search indexes, database search indexes are stored within the tenant database"
+                    );
+                    continue;
+                }
+            },
+            ResourceKind::Secret => secrets_client.list_secrets().await?,
+        };
+
+        let owned = owned_names(config, config_secret, tenants, *kind);
+
+        orphans.extend(
+            names
+                .into_iter()
+                .filter(|name| name.starts_with(prefix))
+                .filter(|name| !owned.contains(name.as_str()))
+                .map(|name| OrphanedResource { kind: *kind, name }),
+        );
+    }
+
+    orphans.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(orphans)
+}
+
+/// Names of the resources of `kind` that are owned by a tenant or
+/// by the deployment itself
+fn owned_names<'a>(
+    config: &'a ServerConfigData,
+    config_secret: Option<&'a str>,
+    tenants: &'a [Tenant],
+    kind: ResourceKind,
+) -> HashSet<&'a str> {
+    let mut owned: HashSet<&str> = tenants
+        .iter()
+        .filter_map(|tenant| match kind {
+            ResourceKind::Database => Some(tenant.db_name.as_str()),
+            ResourceKind::Bucket => Some(tenant.s3_name.as_str()),
+            ResourceKind::SearchIndex => Some(tenant.os_index_name.as_str()),
+            ResourceKind::Secret => tenant.db_secret_name.as_deref(),
+        })
+        .collect();
+
+    match kind {
+        ResourceKind::Database => {
+            owned.insert(ROOT_DATABASE_NAME);
+            owned.extend(SYSTEM_DATABASES);
+        }
+        ResourceKind::Secret => {
+            owned.extend(config_secret);
+            owned.extend(config.database.root_secret_name.as_deref());
+            owned.extend(config.database.setup_user_secret_name.as_deref());
+
+            if let SearchIndexFactoryConfig::Typesense(search) = &config.search {
+                owned.extend(search.api_key_secret_name.as_deref());
+            }
+        }
+        ResourceKind::Bucket | ResourceKind::SearchIndex => {}
+    }
+
+    owned
+}
+
+/// List the names of every non-template database on the database server
+async fn list_databases(db_provider: &impl DatabaseProvider) -> eyre::Result<Vec<String>> {
+    let db = db_provider
+        .connect("postgres")
+        .await
+        .context("failed to connect to postgres database")?;
+
+    let _guard = close_pool_on_drop(&db);
+
+    let names: Vec<String> =
+        sqlx::query_scalar("SELECT datname FROM pg_database WHERE datistemplate = false")
+            .fetch_all(&db)
+            .await
+            .context("failed to list databases")?;
+
+    Ok(names)
+}
+
+/// Delete an orphaned resource, fails if a tenant has started using the
+/// resource since the orphans were found
+pub async fn delete_orphan(
+    db_provider: &impl DatabaseProvider,
+    storage: &StorageLayerFactory,
+    storage_client: &StorageClient,
+    search_client: &SearchClient,
+    secrets: &SecretManager,
+    orphan: &OrphanedResource,
+    permanently_delete_secret: bool,
+) -> eyre::Result<()> {
+    let resource = match orphan.kind {
+        ResourceKind::Database => TenantResource::Database(orphan.name.clone()),
+        ResourceKind::Bucket => TenantResource::Bucket(orphan.name.clone()),
+        ResourceKind::SearchIndex => TenantResource::SearchIndex(orphan.name.clone()),
+        ResourceKind::Secret => TenantResource::Secret(orphan.name.clone()),
+    };
+
+    let filter = TenantFilter {
+        resource: Some(resource),
+        ..Default::default()
+    };
+
+    let owners = find_tenants(db_provider, &filter, None)
+        .await
+        .context("failed to check the resource is still orphaned")?;
+
+    if let Some(owner) = owners.first() {
+        eyre::bail!(
+            "resource is now owned by tenant {} ({})",
+            owner.name,
+            owner.id
+        );
+    }
+
+    match orphan.kind {
+        ResourceKind::Database => {
+            let db = db_provider
+                .connect("postgres")
+                .await
+                .context("failed to connect to postgres database")?;
+
+            let _guard = close_pool_on_drop(&db);
+
+            delete_database(&db, &orphan.name)
+                .await
+                .context("failed to delete database")?;
+        }
+
+        ResourceKind::Bucket => {
+            let storage = storage.create_layer(StorageLayerOptions {
+                bucket_name: orphan.name.clone(),
+            });
+
+            for object in storage_client.list_objects(&orphan.name).await? {
+                storage
+                    .delete_file(&object.key)
+                    .await
+                    .with_context(|| format!("failed to delete object {}", object.key))?;
+            }
+
+            storage
+                .delete_bucket()
+                .await
+                .context("failed to delete bucket")?;
+        }
+
+        ResourceKind::SearchIndex => {
+            search_client.delete_index(&orphan.name).await?;
+        }
+
+        ResourceKind::Secret => {
+            secrets
+                .delete_secret(&orphan.name, permanently_delete_secret)
+                .await
+                .context("failed to delete secret")?;
+        }
+    }
+
+    Ok(())
+}
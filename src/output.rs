@@ -0,0 +1,390 @@
+//! Machine readable output formats
+
+use clap::ValueEnum;
+use eyre::Context;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use serde_json_path::JsonPath;
+
+#[derive(ValueEnum, Clone)]
+pub enum OutputFormat {
+    /// Provide output in human readable format
+    Human,
+
+    /// Provide output in machine readable JSON format
+    Json,
+
+    /// Provide output in machine readable YAML format
+    Yaml,
+
+    /// Provide output as CSV, list outputs have a row for each item
+    Csv,
+
+    /// Provide output as newline delimited JSON, list outputs have a line for each item
+    Ndjson,
+}
+
+/// Output options shared by every command
+#[derive(clap::Args, Clone)]
+pub struct OutputArgs {
+    #[arg(short, long, default_value = "human")]
+    pub format: OutputFormat,
+
+    /// JSONPath expression (i.e "$[*].id") used to filter the output before it
+    /// is printed, implies JSON output when using the human readable format.
+    ///
+    /// The matches are always output as an array, use --single to output a
+    /// single match as is
+    #[arg(short, long)]
+    pub query: Option<JsonPath>,
+
+    /// Output the value matched by the query as is rather than as an array,
+    /// fails unless the query matches exactly one value
+    #[arg(long, requires = "query")]
+    pub single: bool,
+
+    /// Output scalar values as plain text without quotes, arrays of scalars
+    /// are output one value per line. Implies JSON output when using the human
+    /// readable format
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Template (i.e "{{ id }} {{ name }}") to render the output with, arrays
+    /// are rendered once per item on their own line. Objects provide their
+    /// fields to the template, other values are provided as "value"
+    #[arg(long, conflicts_with = "raw")]
+    pub template: Option<String>,
+}
+
+impl OutputArgs {
+    /// Apply the implied format of the other options
+    pub fn resolve(mut self) -> Self {
+        if (self.query.is_some() || self.template.is_some() || self.raw)
+            && matches!(self.format, OutputFormat::Human)
+        {
+            self.format = OutputFormat::Json;
+        }
+
+        self
+    }
+}
+
+/// Print a serializable `value` in the machine readable output format
+///
+/// For CSV and NDJSON a top level array produces one row or line per item
+pub fn print_output<T: Serialize + ?Sized>(output: &OutputArgs, value: &T) -> eyre::Result<()> {
+    let value = apply_query(output, serde_json::to_value(value)?)?;
+
+    if let Some(template) = output.template.as_deref() {
+        return print_template(template, value);
+    }
+
+    if output.raw && print_raw(&value) {
+        return Ok(());
+    }
+
+    match output.format {
+        OutputFormat::Human | OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        OutputFormat::Yaml => {
+            print!("{}", serde_norway::to_string(&value)?);
+        }
+        OutputFormat::Ndjson => {
+            for item in into_items(value) {
+                println!("{}", serde_json::to_string(&item)?);
+            }
+        }
+        OutputFormat::Csv => {
+            let rows: Vec<Map<String, Value>> = into_items(value)
+                .into_iter()
+                .map(|item| {
+                    let mut row = Map::new();
+                    flatten_value(&mut row, None, item);
+                    row
+                })
+                .collect();
+
+            write_csv(&rows)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply the output query to the `value`, the matches are provided as an array
+/// unless a single match was requested
+fn apply_query(output: &OutputArgs, value: Value) -> eyre::Result<Value> {
+    let Some(query) = output.query.as_ref() else {
+        return Ok(value);
+    };
+
+    let nodes = query.query(&value);
+
+    if output.single {
+        return Ok(nodes
+            .exactly_one()
+            .context("query must match exactly one value when using --single")?
+            .clone());
+    }
+
+    Ok(Value::Array(nodes.all().into_iter().cloned().collect()))
+}
+
+/// Print the `value` in the machine readable output format using `rows` for
+/// the row based formats (CSV, NDJSON), used for outputs that wrap a list of
+/// tenants in an object. Templates are also rendered for each row
+///
+/// Queries are always applied to the `value` so a query selects the same
+/// data for every format
+pub fn print_output_rows<T, R>(output: &OutputArgs, value: &T, rows: &[R]) -> eyre::Result<()>
+where
+    T: Serialize + ?Sized,
+    R: Serialize,
+{
+    if output.query.is_some() {
+        return print_output(output, value);
+    }
+
+    if output.template.is_some() {
+        return print_output(output, rows);
+    }
+
+    match output.format {
+        OutputFormat::Csv | OutputFormat::Ndjson => print_output(output, rows),
+        _ => print_output(output, value),
+    }
+}
+
+/// Render each item of the `value` with the `template`
+fn print_template(template: &str, value: Value) -> eyre::Result<()> {
+    let mut env = minijinja::Environment::new();
+    env.add_template("output", template)
+        .context("failed to parse template")?;
+    let template = env.get_template("output")?;
+
+    for item in into_items(value) {
+        let context = match item {
+            Value::Object(_) => minijinja::Value::from_serialize(&item),
+            item => minijinja::context! { value => item },
+        };
+
+        let rendered = template
+            .render(context)
+            .context("failed to render template")?;
+        println!("{rendered}");
+    }
+
+    Ok(())
+}
+
+/// Print scalars and arrays of scalars as plain text, returns false if the
+/// value is not made up of scalars
+fn print_raw(value: &Value) -> bool {
+    match value {
+        Value::Array(items) if items.iter().all(is_scalar) => {
+            for item in items {
+                println!("{}", raw_scalar(item));
+            }
+            true
+        }
+        value if is_scalar(value) => {
+            println!("{}", raw_scalar(value));
+            true
+        }
+        _ => false,
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn raw_scalar(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Split a value into its items, only arrays have more than one item
+fn into_items(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        value => vec![value],
+    }
+}
+
+/// Flatten nested objects into `row` using dot separated keys
+fn flatten_value(row: &mut Map<String, Value>, prefix: Option<&str>, value: Value) {
+    match (value, prefix) {
+        (Value::Object(object), _) => {
+            for (key, value) in object {
+                let key = match prefix {
+                    Some(prefix) => format!("{prefix}.{key}"),
+                    None => key,
+                };
+                flatten_value(row, Some(&key), value);
+            }
+        }
+        (value, Some(prefix)) => {
+            row.insert(prefix.to_string(), value);
+        }
+        (value, None) => {
+            row.insert("value".to_string(), value);
+        }
+    }
+}
+
+/// Text for a single CSV cell
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        Value::Array(items) if items.iter().all(|item| !item.is_object()) => {
+            items.iter().map(csv_cell).collect::<Vec<_>>().join(";")
+        }
+        value => value.to_string(),
+    }
+}
+
+fn write_csv(rows: &[Map<String, Value>]) -> eyre::Result<()> {
+    // Columns are the union of every row in the order they first appear
+    let mut headers: Vec<&String> = Vec::new();
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if !headers.contains(&key) {
+            headers.push(key);
+        }
+    }
+
+    if headers.is_empty() {
+        return Ok(());
+    }
+
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(&headers)?;
+
+    for row in rows {
+        writer.write_record(
+            headers
+                .iter()
+                .map(|header| row.get(*header).map(csv_cell).unwrap_or_default()),
+        )?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OutputArgs, OutputFormat, apply_query, csv_cell, flatten_value};
+    use serde_json::{Map, Value, json};
+
+    fn output(query: &str, single: bool) -> OutputArgs {
+        OutputArgs {
+            format: OutputFormat::Json,
+            query: Some(query.parse().unwrap()),
+            single,
+            raw: false,
+            template: None,
+        }
+    }
+
+    /// Tests that the raw and query options imply JSON over the human format
+    #[test]
+    fn test_resolve_implies_json() {
+        let raw = OutputArgs {
+            format: OutputFormat::Human,
+            query: None,
+            single: false,
+            raw: true,
+            template: None,
+        };
+        assert!(matches!(raw.resolve().format, OutputFormat::Json));
+
+        let query = OutputArgs {
+            format: OutputFormat::Human,
+            ..output("$.id", false)
+        };
+        assert!(matches!(query.resolve().format, OutputFormat::Json));
+
+        let csv = OutputArgs {
+            format: OutputFormat::Csv,
+            ..output("$.id", false)
+        };
+        assert!(matches!(csv.resolve().format, OutputFormat::Csv));
+    }
+
+    /// Tests that query matches are an array regardless of the number of matches
+    #[test]
+    fn test_query_matches_array() {
+        let value = json!({ "tenants": [{ "id": 1, "env": "prod" }, { "id": 2, "env": "dev" }] });
+        let query = output("$.tenants[?@.env=='prod'].id", false);
+
+        assert_eq!(apply_query(&query, value.clone()).unwrap(), json!([1]));
+        assert_eq!(
+            apply_query(&output("$.tenants[*].id", false), value.clone()).unwrap(),
+            json!([1, 2])
+        );
+        assert_eq!(
+            apply_query(&output("$.missing", false), value).unwrap(),
+            json!([])
+        );
+    }
+
+    /// Tests that a single match is output as is when requested
+    #[test]
+    fn test_query_single() {
+        let value = json!({ "tenants": [{ "id": 1 }, { "id": 2 }] });
+
+        assert_eq!(
+            apply_query(&output("$.tenants[0].id", true), value.clone()).unwrap(),
+            json!(1)
+        );
+        assert!(apply_query(&output("$.tenants[*].id", true), value.clone()).is_err());
+        assert!(apply_query(&output("$.missing", true), value).is_err());
+    }
+
+    fn flatten(value: Value) -> Map<String, Value> {
+        let mut row = Map::new();
+        flatten_value(&mut row, None, value);
+        row
+    }
+
+    /// Tests that nested objects are flattened into dotted keys
+    #[test]
+    fn test_flatten_nested() {
+        let row = flatten(json!({
+            "id": 1,
+            "usage": { "size": 2, "largest": { "key": "a" } },
+            "error": null
+        }));
+
+        assert_eq!(row["id"], json!(1));
+        assert_eq!(row["usage.size"], json!(2));
+        assert_eq!(row["usage.largest.key"], json!("a"));
+        assert_eq!(row["error"], Value::Null);
+        assert_eq!(row.len(), 4);
+    }
+
+    /// Tests that non object rows are stored under a "value" key
+    #[test]
+    fn test_flatten_scalar() {
+        let row = flatten(json!("tenant"));
+        assert_eq!(row["value"], json!("tenant"));
+        assert_eq!(row.len(), 1);
+    }
+
+    /// Tests the formatting of values within CSV cells
+    #[test]
+    fn test_csv_cell() {
+        assert_eq!(csv_cell(&Value::Null), "");
+        assert_eq!(csv_cell(&json!("text")), "text");
+        assert_eq!(csv_cell(&json!(12)), "12");
+        assert_eq!(csv_cell(&json!(true)), "true");
+        assert_eq!(csv_cell(&json!(["a", "b", 1])), "a;b;1");
+        assert_eq!(csv_cell(&json!([{ "a": 1 }])), r#"[{"a":1}]"#);
+    }
+}
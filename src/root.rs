@@ -0,0 +1,164 @@
+//! Inspection of the root database state of a deployment
+
+use docbox_management::{
+    config::AdminDatabaseConfiguration,
+    core::secrets::SecretManager,
+    database::{
+        DatabaseProvider, ROOT_DATABASE_NAME, close_pool_on_drop,
+        create::check_database_table_exists, migrations::ROOT_MIGRATIONS,
+        models::root_migration::RootMigration, sqlx,
+    },
+    root::{get_pending_root_migrations::get_pending_root_migrations, initialize::is_initialized},
+};
+use eyre::Context;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Authentication mode the API server uses for the root database
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RootAuthMode {
+    Iam,
+    Secret,
+    NotConfigured,
+}
+
+impl RootAuthMode {
+    pub fn from_config(config: &AdminDatabaseConfiguration) -> Self {
+        if config.root_iam {
+            Self::Iam
+        } else if config.root_secret_name.is_some() {
+            Self::Secret
+        } else {
+            Self::NotConfigured
+        }
+    }
+}
+
+impl std::fmt::Display for RootAuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RootAuthMode::Iam => "IAM",
+            RootAuthMode::Secret => "Secret",
+            RootAuthMode::NotConfigured => "Not Configured",
+        })
+    }
+}
+
+/// Redacted connection details for the root database
+#[derive(Serialize)]
+pub struct RootConnection {
+    pub host: String,
+    pub port: u16,
+    pub database: &'static str,
+    /// Username of the inline setup user
+    pub setup_user: Option<String>,
+    /// Name of the secret containing the setup user
+    pub setup_user_secret_name: Option<String>,
+}
+
+/// State of the root database
+#[derive(Serialize)]
+pub struct RootStatus {
+    pub connection: RootConnection,
+    pub auth_mode: RootAuthMode,
+    pub root_secret_name: Option<String>,
+    /// Whether the root secret exists, when using secret authentication
+    pub root_secret_exists: Option<bool>,
+    pub initialized: bool,
+    pub server_version: String,
+    pub applied_migrations: Vec<RootMigration>,
+    pub pending_migrations: Vec<String>,
+    /// Number of tenants within each environment
+    pub tenant_counts: BTreeMap<String, usize>,
+}
+
+/// Collect the current state of the root database
+pub async fn get_root_status(
+    db_provider: &impl DatabaseProvider,
+    secrets: &SecretManager,
+    config: &AdminDatabaseConfiguration,
+) -> eyre::Result<RootStatus> {
+    let auth_mode = RootAuthMode::from_config(config);
+
+    let root_secret_exists = match config.root_secret_name.as_deref() {
+        Some(root_secret_name) if !config.root_iam => Some(
+            secrets
+                .has_secret(root_secret_name)
+                .await
+                .context("failed to check root secret")?,
+        ),
+        _ => None,
+    };
+
+    let initialized = is_initialized(db_provider)
+        .await
+        .context("failed to check root initialized")?;
+
+    // Root database may not exist yet, fallback to the default database
+    let db = db_provider
+        .connect(if initialized {
+            ROOT_DATABASE_NAME
+        } else {
+            "postgres"
+        })
+        .await
+        .context("failed to connect to database")?;
+
+    let _guard = close_pool_on_drop(&db);
+
+    let server_version: String = sqlx::query_scalar("SHOW server_version")
+        .fetch_one(&db)
+        .await
+        .context("failed to get server version")?;
+
+    let mut applied_migrations = Vec::new();
+    let mut pending_migrations: Vec<String> = ROOT_MIGRATIONS
+        .iter()
+        .map(|(migration_name, _migration)| migration_name.to_string())
+        .collect();
+    let mut tenant_counts = BTreeMap::new();
+
+    if initialized {
+        if check_database_table_exists(&db, "docbox_root_migrations")
+            .await
+            .context("failed to check root migrations table")?
+        {
+            applied_migrations = RootMigration::all(&db)
+                .await
+                .context("failed to get applied root migrations")?;
+        }
+
+        pending_migrations = get_pending_root_migrations(db_provider)
+            .await
+            .context("failed to get pending root migrations")?;
+
+        let tenants = docbox_management::tenant::get_tenants::get_tenants(db_provider).await?;
+        for tenant in tenants {
+            *tenant_counts.entry(tenant.env).or_default() += 1;
+        }
+    }
+
+    applied_migrations.sort_by_key(|migration| migration.applied_at);
+
+    Ok(RootStatus {
+        connection: RootConnection {
+            host: config.host.clone(),
+            port: config.port,
+            database: ROOT_DATABASE_NAME,
+            setup_user: config
+                .setup_user
+                .as_ref()
+                .map(|setup_user| setup_user.username.clone()),
+            setup_user_secret_name: config.setup_user_secret_name.clone(),
+        },
+        auth_mode,
+        root_secret_name: config.root_secret_name.clone(),
+        root_secret_exists,
+        initialized,
+        server_version,
+        applied_migrations,
+        pending_migrations,
+        tenant_counts,
+    })
+}
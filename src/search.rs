@@ -0,0 +1,309 @@
+//! Direct access to the search backend for operations that are not
+//! exposed through the docbox [SearchIndexFactory](docbox_management::core::search::SearchIndexFactory)
+
+use aws_config::SdkConfig;
+use docbox_management::core::{
+    search::SearchIndexFactoryConfig,
+    secrets::{Secret, SecretManager},
+};
+use eyre::{Context, ContextCompat};
+use opensearch::{
+    CountParts, OpenSearch,
+    cat::CatIndicesParts,
+    http::{
+        StatusCode, Url,
+        transport::{SingleNodeConnectionPool, TransportBuilder},
+    },
+    indices::IndicesDeleteParts,
+};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+
+/// Characters to encode within a URL path segment
+const PATH_SEGMENT_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Client for accessing the search backend
+pub enum SearchClient {
+    /// Typesense search backend
+    Typesense {
+        client: reqwest::Client,
+        url: String,
+        api_key: String,
+    },
+
+    /// OpenSearch search backend
+    OpenSearch(OpenSearch),
+
+    /// Database search backend, search indexes are stored within
+    /// the tenant databases
+    Database,
+}
+
+/// Entry from the OpenSearch cat indices API
+#[derive(Deserialize)]
+struct CatIndex {
+    index: String,
+}
+
+/// Collection from the Typesense collections API
+#[derive(Deserialize)]
+struct TypesenseCollection {
+    name: String,
+}
+
+/// Collection details from the Typesense collection API
+#[derive(Deserialize)]
+struct TypesenseCollectionDetails {
+    num_documents: u64,
+}
+
+/// Response from the OpenSearch count API
+#[derive(Deserialize)]
+struct OpenSearchCount {
+    count: u64,
+}
+
+impl SearchClient {
+    /// Create a [SearchClient] using the same backend as the docbox search factory
+    pub async fn from_config(
+        aws_config: &SdkConfig,
+        secrets: &SecretManager,
+        config: &SearchIndexFactoryConfig,
+    ) -> eyre::Result<Self> {
+        match config {
+            SearchIndexFactoryConfig::Typesense(config) => {
+                let api_key = match (
+                    config.api_key.as_ref(),
+                    config.api_key_secret_name.as_deref(),
+                ) {
+                    (Some(api_key), _) => serde_json::to_value(api_key)?
+                        .as_str()
+                        .context("typesense api key must be a string")?
+                        .to_string(),
+                    (_, Some(secret_name)) => match secrets
+                        .get_secret(secret_name)
+                        .await
+                        .context("failed to get typesense api key secret")?
+                    {
+                        Some(Secret::String(value)) => value,
+                        Some(Secret::Binary(_)) => {
+                            eyre::bail!("expected string secret for typesense api key")
+                        }
+                        None => eyre::bail!("typesense api key secret not found"),
+                    },
+                    _ => eyre::bail!("must provide either api_key or api_key_secret_name"),
+                };
+
+                let client = reqwest::Client::builder()
+                    // Don't try and proxy through the proxy
+                    .no_proxy()
+                    .build()
+                    .context("failed to create typesense http client")?;
+
+                Ok(Self::Typesense {
+                    client,
+                    url: config.url.clone(),
+                    api_key,
+                })
+            }
+
+            SearchIndexFactoryConfig::OpenSearch(config) => {
+                let url = Url::parse(&config.url).context("failed to parse opensearch url")?;
+                let conn_pool = SingleNodeConnectionPool::new(url);
+
+                let transport = TransportBuilder::new(conn_pool)
+                    .disable_proxy()
+                    .auth(
+                        aws_config
+                            .clone()
+                            .try_into()
+                            .context("failed to create opensearch auth config")?,
+                    )
+                    .service_name("es")
+                    .build()
+                    .context("failed to build opensearch transport")?;
+
+                Ok(Self::OpenSearch(OpenSearch::new(transport)))
+            }
+
+            SearchIndexFactoryConfig::Database(_) => Ok(Self::Database),
+        }
+    }
+
+    /// List the names of every search index, for the database backend
+    /// this is [None] as the indexes are part of the tenant databases
+    pub async fn list_indexes(&self) -> eyre::Result<Option<Vec<String>>> {
+        match self {
+            SearchClient::Typesense {
+                client,
+                url,
+                api_key,
+            } => {
+                let collections: Vec<TypesenseCollection> = client
+                    .get(format!("{url}/collections"))
+                    .header("x-typesense-api-key", api_key)
+                    .send()
+                    .await
+                    .context("failed to list search indexes")?
+                    .error_for_status()
+                    .context("failed to list search indexes")?
+                    .json()
+                    .await
+                    .context("failed to parse search indexes")?;
+
+                Ok(Some(
+                    collections
+                        .into_iter()
+                        .map(|collection| collection.name)
+                        .collect(),
+                ))
+            }
+
+            SearchClient::OpenSearch(client) => {
+                let indexes: Vec<CatIndex> = client
+                    .cat()
+                    .indices(CatIndicesParts::None)
+                    .format("json")
+                    .send()
+                    .await
+                    .context("failed to list search indexes")?
+                    .error_for_status_code()
+                    .context("failed to list search indexes")?
+                    .json()
+                    .await
+                    .context("failed to parse search indexes")?;
+
+                Ok(Some(
+                    indexes
+                        .into_iter()
+                        .map(|index| index.index)
+                        // Exclude internal indexes
+                        .filter(|index| !index.starts_with('.'))
+                        .collect(),
+                ))
+            }
+
+            SearchClient::Database => Ok(None),
+        }
+    }
+
+    /// Delete a search index by `name`
+    pub async fn delete_index(&self, name: &str) -> eyre::Result<()> {
+        match self {
+            SearchClient::Typesense {
+                client,
+                url,
+                api_key,
+            } => {
+                let response = client
+                    .delete(collection_url(url, name))
+                    .header("x-typesense-api-key", api_key)
+                    .send()
+                    .await
+                    .context("failed to delete search index")?;
+
+                // Gracefully handle the index already not existing
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(());
+                }
+
+                response
+                    .error_for_status()
+                    .context("failed to delete search index")?;
+            }
+
+            SearchClient::OpenSearch(client) => {
+                let response = client
+                    .indices()
+                    .delete(IndicesDeleteParts::Index(&[name]))
+                    .send()
+                    .await
+                    .context("failed to delete search index")?;
+
+                // Gracefully handle the index already not existing
+                if response.status_code() == StatusCode::NOT_FOUND {
+                    return Ok(());
+                }
+
+                response
+                    .error_for_status_code()
+                    .context("failed to delete search index")?;
+            }
+
+            SearchClient::Database => {
+                eyre::bail!("database search indexes are stored within the tenant database")
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of documents within the search index `name`, provides
+    /// [None] when the index does not exist
+    pub async fn get_index_document_count(&self, name: &str) -> eyre::Result<Option<u64>> {
+        match self {
+            SearchClient::Typesense {
+                client,
+                url,
+                api_key,
+            } => {
+                let response = client
+                    .get(collection_url(url, name))
+                    .header("x-typesense-api-key", api_key)
+                    .send()
+                    .await
+                    .context("failed to get search index")?;
+
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+
+                let collection: TypesenseCollectionDetails = response
+                    .error_for_status()
+                    .context("failed to get search index")?
+                    .json()
+                    .await
+                    .context("failed to parse search index")?;
+
+                Ok(Some(collection.num_documents))
+            }
+
+            SearchClient::OpenSearch(client) => {
+                let response = client
+                    .count(CountParts::Index(&[name]))
+                    .send()
+                    .await
+                    .context("failed to count search index documents")?;
+
+                if response.status_code() == StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+
+                let count: OpenSearchCount = response
+                    .error_for_status_code()
+                    .context("failed to count search index documents")?
+                    .json()
+                    .await
+                    .context("failed to parse search index document count")?;
+
+                Ok(Some(count.count))
+            }
+
+            SearchClient::Database => {
+                eyre::bail!("database search indexes are stored within the tenant database")
+            }
+        }
+    }
+}
+
+/// URL of the Typesense collection `name`
+fn collection_url(url: &str, name: &str) -> String {
+    format!(
+        "{url}/collections/{}",
+        utf8_percent_encode(name, PATH_SEGMENT_ENCODE_SET)
+    )
+}
@@ -0,0 +1,114 @@
+//! Direct access to the secrets manager for operations that are not
+//! exposed through the docbox [SecretManager](docbox_management::core::secrets::SecretManager)
+
+use aws_config::SdkConfig;
+use aws_sdk_secretsmanager::{
+    config::{Credentials, SharedCredentialsProvider},
+    operation::create_secret::CreateSecretError,
+};
+use docbox_management::core::secrets::{SecretsManagerConfig, aws::AwsSecretsEndpoint};
+use eyre::Context;
+
+type SecretsManagerClient = aws_sdk_secretsmanager::Client;
+
+/// Client for accessing the secrets manager
+pub enum SecretsClient {
+    /// AWS secrets manager
+    Aws(SecretsManagerClient),
+
+    /// In-memory secrets manager with the names of the configured secrets
+    Memory(Vec<String>),
+}
+
+impl SecretsClient {
+    /// Create a [SecretsClient] using the same backend as the docbox secrets manager
+    pub fn from_config(aws_config: &SdkConfig, config: &SecretsManagerConfig) -> Self {
+        match config {
+            SecretsManagerConfig::Aws(config) => {
+                let client = match &config.endpoint {
+                    AwsSecretsEndpoint::Aws => SecretsManagerClient::new(aws_config),
+                    AwsSecretsEndpoint::Custom {
+                        endpoint,
+                        access_key_id,
+                        access_key_secret,
+                    } => {
+                        // Apply custom credentials and endpoint
+                        let credentials = Credentials::new(
+                            access_key_id,
+                            access_key_secret,
+                            None,
+                            None,
+                            "docbox",
+                        );
+                        let aws_config = aws_config
+                            .to_builder()
+                            .endpoint_url(endpoint)
+                            .credentials_provider(SharedCredentialsProvider::new(credentials))
+                            .build();
+                        SecretsManagerClient::new(&aws_config)
+                    }
+                };
+
+                Self::Aws(client)
+            }
+
+            SecretsManagerConfig::Memory(config) => {
+                Self::Memory(config.secrets.keys().cloned().collect())
+            }
+        }
+    }
+
+    /// List the names of every secret
+    pub async fn list_secrets(&self) -> eyre::Result<Vec<String>> {
+        match self {
+            SecretsClient::Aws(client) => {
+                let mut names = Vec::new();
+                let mut pages = client.list_secrets().into_paginator().send();
+
+                while let Some(page) = pages.next().await {
+                    let page = page.context("failed to list secrets")?;
+                    names.extend(
+                        page.secret_list()
+                            .iter()
+                            .filter_map(|secret| secret.name())
+                            .map(|name| name.to_string()),
+                    );
+                }
+
+                Ok(names)
+            }
+
+            SecretsClient::Memory(names) => Ok(names.clone()),
+        }
+    }
+
+    /// Create a new secret `name` containing `value`, fails when the secret
+    /// already exists rather than replacing its value
+    pub async fn create_secret(&self, name: &str, value: &str) -> eyre::Result<()> {
+        match self {
+            SecretsClient::Aws(client) => {
+                if let Err(error) = client
+                    .create_secret()
+                    .name(name)
+                    .secret_string(value)
+                    .send()
+                    .await
+                {
+                    if let Some(CreateSecretError::ResourceExistsException(_)) =
+                        error.as_service_error()
+                    {
+                        eyre::bail!("secret {name} already exists");
+                    }
+
+                    return Err(error).with_context(|| format!("failed to create secret {name}"));
+                }
+
+                Ok(())
+            }
+
+            SecretsClient::Memory(_) => {
+                eyre::bail!("creating secrets is not supported by the in-memory secrets manager")
+            }
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Interactive shell that runs commands against a single set of loaded
+//! backends, avoiding reloading the config and reconnecting for every command
+
+use crate::{
+    Commands, backends::Backends, command_name, completion::complete_env, context::ConfigSource,
+    output::OutputArgs, print_error, run_command,
+};
+use aws_config::SdkConfig;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use docbox_management::config::ServerConfigData;
+use eyre::Context;
+use rustyline::{
+    CompletionType, Config, Editor, Helper,
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+};
+use std::{ffi::OsString, path::PathBuf};
+
+/// Command entered within the shell
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct ShellArgs {
+    #[command(subcommand)]
+    command: Commands,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// Commands built into the shell rather than the cli
+const BUILTINS: [&str; 3] = ["env", "exit", "quit"];
+
+/// Loaded server state shared by the shell commands
+pub struct ShellContext<'a> {
+    pub aws_config: &'a SdkConfig,
+    pub config: &'a ServerConfigData,
+    pub backends: &'a Backends<'a>,
+    /// Where the config was loaded from
+    pub source: &'a ConfigSource,
+}
+
+/// Run the shell until the user exits, `env` is the initial environment
+/// used for commands that do not specify one
+pub async fn run_shell(ctx: ShellContext<'_>, mut env: Option<String>) -> eyre::Result<()> {
+    let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::with_config(
+        Config::builder()
+            .completion_type(CompletionType::List)
+            .auto_add_history(true)
+            .build(),
+    )
+    .context("failed to create shell editor")?;
+    editor.set_helper(Some(ShellHelper));
+
+    let history_path = history_path();
+    if let Some(path) = history_path.as_ref() {
+        // History does not exist on first use
+        _ = editor.load_history(path);
+    }
+
+    println!("docbox shell, enter a command (i.e get-tenants), help or exit to quit");
+    println!("use \"env <env>\" to set the environment for commands that do not specify one");
+
+    loop {
+        let prompt = match (&ctx.source.context, &env) {
+            (Some(context), Some(env)) => format!("{context} ({env})> "),
+            (Some(context), None) => format!("{context}> "),
+            (None, Some(env)) => format!("docbox ({env})> "),
+            (None, None) => "docbox> ".to_string(),
+        };
+
+        let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+            Ok(line) => line,
+            // Ctrl+C clears the current line
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error).context("failed to read shell input"),
+        };
+
+        let Some(mut words) = shlex::split(&line) else {
+            eprintln!("error: unterminated quote");
+            continue;
+        };
+
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some("env") => {
+                env = words.get(1).cloned();
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        if let Some(env) = env.as_deref() {
+            insert_default_env(&mut words, env);
+        }
+
+        let matches = match ShellArgs::command().try_get_matches_from(&words) {
+            Ok(matches) => matches,
+            Err(error) => {
+                _ = error.print();
+                continue;
+            }
+        };
+
+        let command = command_name(&matches);
+        let args = match ShellArgs::from_arg_matches(&matches) {
+            Ok(args) => args,
+            Err(error) => {
+                _ = error.print();
+                continue;
+            }
+        };
+
+        if let Commands::Context { .. }
+        | Commands::Config { .. }
+        | Commands::Completions { .. }
+        | Commands::Man { .. }
+        | Commands::Shell { .. }
+        | Commands::Tui = args.command
+        {
+            eprintln!(
+                "error: {} is not available within the shell",
+                command.unwrap_or_default()
+            );
+            continue;
+        }
+
+        let output = args.output.resolve();
+
+        if let Err(error) = run_command(
+            ctx.aws_config,
+            ctx.config,
+            ctx.source,
+            ctx.backends,
+            &output,
+            args.command,
+        )
+        .await
+        {
+            // Keep the shell running when the error cannot be rendered
+            if let Err(print_failure) = print_error(&output, &error, command) {
+                tracing::error!(?error, ?print_failure, "failed to print command error");
+            }
+        }
+    }
+
+    if let Some(path) = history_path.as_ref() {
+        if let Some(parent) = path.parent() {
+            _ = std::fs::create_dir_all(parent);
+        }
+
+        if let Err(error) = editor.save_history(path) {
+            tracing::warn!(?error, "failed to save shell history");
+        }
+    }
+
+    Ok(())
+}
+
+/// Path to the shell history file
+fn history_path() -> Option<PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("docbox-cli")
+            .join("shell_history.txt"),
+    )
+}
+
+/// Insert "--env `env`" after the subcommand when the subcommand accepts an
+/// environment that was not provided
+fn insert_default_env(words: &mut Vec<String>, env: &str) {
+    let command = ShellArgs::command();
+
+    let Some((index, subcommand)) = words
+        .iter()
+        .enumerate()
+        .find_map(|(index, word)| Some((index, command.find_subcommand(word)?)))
+    else {
+        return;
+    };
+
+    let accepts_env = subcommand
+        .get_arguments()
+        .any(|argument| argument.get_id() == "env");
+
+    if accepts_env && !has_env_arg(subcommand, &words[index + 1..]) {
+        words.splice(index + 1..index + 1, ["--env".to_string(), env.to_string()]);
+    }
+}
+
+/// Whether the `words` following a subcommand provide its env argument,
+/// values of other arguments are skipped so a value such as "-eu" is not
+/// mistaken for the env flag
+fn has_env_arg(subcommand: &clap::Command, words: &[String]) -> bool {
+    let takes_value = |argument: Option<&clap::Arg>| {
+        argument.is_some_and(|argument| argument.get_action().takes_values())
+    };
+
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        if word == "--" {
+            break;
+        }
+
+        if let Some(long) = word.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+
+            if name == "env" {
+                return true;
+            }
+
+            let argument = subcommand
+                .get_arguments()
+                .find(|argument| argument.get_long() == Some(name));
+            if value.is_none() && takes_value(argument) {
+                words.next();
+            }
+        } else if let Some(shorts) = word.strip_prefix('-') {
+            // Short flags may be bundled (i.e "-te"), the rest of the word
+            // after a short that takes a value is that value
+            for (position, short) in shorts.char_indices() {
+                if short == 'e' {
+                    return true;
+                }
+
+                let argument = subcommand
+                    .get_arguments()
+                    .find(|argument| argument.get_short() == Some(short));
+                if takes_value(argument) {
+                    if position + short.len_utf8() == shorts.len() {
+                        words.next();
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// Tab completion of commands and their arguments
+struct ShellHelper;
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+
+        // Start of the word being completed
+        let start = line
+            .rfind(char::is_whitespace)
+            .map(|index| index + 1)
+            .unwrap_or_default();
+        let current = &line[start..];
+
+        let mut words: Vec<String> = shlex::split(&line[..start])
+            .unwrap_or_else(|| line[..start].split_whitespace().map(String::from).collect());
+
+        if words.first().is_some_and(|word| word == "env") {
+            let candidates = match words.len() {
+                1 => complete_env()
+                    .into_iter()
+                    .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+                    .filter(|value| value.starts_with(current))
+                    .map(|value| Pair {
+                        display: value.clone(),
+                        replacement: value,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            return Ok((start, candidates));
+        }
+
+        words.push(current.to_string());
+        let index = words.len() - 1;
+
+        let mut candidates: Vec<Pair> = clap_complete::engine::complete(
+            &mut ShellArgs::command(),
+            words.into_iter().map(OsString::from).collect(),
+            index,
+            None,
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|candidate| !candidate.is_hide_set())
+        .map(|candidate| {
+            let value = candidate.get_value().to_string_lossy().into_owned();
+            let display = match candidate.get_help() {
+                Some(help) => format!("{value}  {help}"),
+                None => value.clone(),
+            };
+
+            Pair {
+                display,
+                replacement: value,
+            }
+        })
+        .collect();
+
+        if index == 0 {
+            candidates.extend(
+                BUILTINS
+                    .iter()
+                    .filter(|builtin| builtin.starts_with(current))
+                    .map(|builtin| Pair {
+                        display: builtin.to_string(),
+                        replacement: builtin.to_string(),
+                    }),
+            );
+        }
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+#[cfg(test)]
+mod test {
+    use super::{has_env_arg, insert_default_env};
+
+    fn words(line: &str) -> Vec<String> {
+        shlex::split(line).unwrap()
+    }
+
+    /// Tests that the env is inserted after the subcommand
+    #[test]
+    fn test_insert_default_env() {
+        let mut line = words("get-tenants --format json");
+        insert_default_env(&mut line, "dev");
+        assert_eq!(line, words("get-tenants --env dev --format json"));
+    }
+
+    /// Tests that an explicitly provided env is kept
+    #[test]
+    fn test_insert_default_env_explicit() {
+        for explicit in ["get-tenants --env prod", "get-tenants -e prod"] {
+            let mut line = words(explicit);
+            insert_default_env(&mut line, "dev");
+            assert_eq!(line, words(explicit));
+        }
+    }
+
+    /// Command with an env, a value taking short and a bundleable flag
+    fn env_command() -> clap::Command {
+        clap::Command::new("test").args([
+            clap::Arg::new("env").short('e').long("env"),
+            clap::Arg::new("name").short('n').long("name"),
+            clap::Arg::new("force")
+                .short('t')
+                .long("force")
+                .action(clap::ArgAction::SetTrue),
+        ])
+    }
+
+    /// Tests that the env is found within bundled short flags and with an
+    /// attached value
+    #[test]
+    fn test_has_env_arg() {
+        let command = env_command();
+
+        for line in ["-e prod", "-eprod", "-te prod", "--env prod", "--env=prod"] {
+            assert!(has_env_arg(&command, &words(line)), "{line}");
+        }
+    }
+
+    /// Tests that values of other arguments are not mistaken for the env
+    #[test]
+    fn test_has_env_arg_values() {
+        let command = env_command();
+
+        for line in [
+            "-n -eu",
+            "-neu",
+            "--name -eu",
+            "--name=-eu",
+            "--envs",
+            "-t",
+            "-- -e",
+        ] {
+            assert!(!has_env_arg(&command, &words(line)), "{line}");
+        }
+    }
+
+    /// Tests that a value starting with "-e" does not prevent the env insertion
+    #[test]
+    fn test_insert_default_env_value() {
+        let mut line = words("get-tenants --name -eu");
+        insert_default_env(&mut line, "dev");
+        assert_eq!(line, words("get-tenants --env dev --name -eu"));
+    }
+
+    /// Tests that commands without an env argument are left unchanged
+    #[test]
+    fn test_insert_default_env_not_accepted() {
+        let mut line = words("migrate-root");
+        insert_default_env(&mut line, "dev");
+        assert_eq!(line, words("migrate-root"));
+    }
+}
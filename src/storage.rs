@@ -0,0 +1,830 @@
+//! Direct access to the tenant storage buckets for operations that are
+//! not exposed through the docbox [StorageLayer](docbox_management::core::storage::StorageLayer)
+
+use aws_config::SdkConfig;
+use aws_sdk_s3::{
+    config::Credentials,
+    types::{
+        CompletedMultipartUpload, CompletedPart, MetadataDirective, NotificationConfiguration,
+    },
+};
+use docbox_management::core::storage::{StorageLayer, StorageLayerFactoryConfig, s3::S3Endpoint};
+use eyre::{Context, ContextCompat};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+use tracing::Instrument;
+use tracing_indicatif::{span_ext::IndicatifSpanExt, style::ProgressStyle};
+
+type S3Client = aws_sdk_s3::Client;
+
+/// Largest object that can be copied in a single request (5 GiB)
+const MAX_COPY_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Characters to encode within the key of a copy source, keys are encoded
+/// the same as a URL path
+const COPY_SOURCE_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Client for accessing tenant storage buckets
+#[derive(Clone)]
+pub struct StorageClient {
+    client: S3Client,
+}
+
+impl StorageClient {
+    /// Create a [StorageClient] using the same endpoint and credentials
+    /// as the docbox storage layer
+    pub fn from_config(aws_config: &SdkConfig, config: &StorageLayerFactoryConfig) -> Self {
+        let StorageLayerFactoryConfig::S3(config) = config;
+
+        let client = match &config.endpoint {
+            S3Endpoint::Aws => S3Client::new(aws_config),
+            S3Endpoint::Custom {
+                endpoint,
+                access_key_id,
+                access_key_secret,
+                ..
+            } => {
+                let credentials = Credentials::new(
+                    access_key_id,
+                    access_key_secret,
+                    None,
+                    None,
+                    "docbox_key_provider",
+                );
+
+                // Enforces the "path" style for S3 bucket access
+                let config = aws_sdk_s3::config::Builder::from(aws_config)
+                    .force_path_style(true)
+                    .endpoint_url(endpoint)
+                    .credentials_provider(credentials)
+                    .build();
+
+                S3Client::from_conf(config)
+            }
+        };
+
+        Self { client }
+    }
+
+    /// Get the allowed CORS origins currently set on the bucket, provides [None]
+    /// when the storage backend does not support reading the CORS configuration
+    ///
+    /// Buckets without any CORS configuration have no allowed origins
+    pub async fn get_bucket_cors_origins(
+        &self,
+        bucket_name: &str,
+    ) -> eyre::Result<Option<Vec<String>>> {
+        let output = match self
+            .client
+            .get_bucket_cors()
+            .bucket(bucket_name)
+            .send()
+            .await
+        {
+            Ok(value) => value,
+            Err(error) => {
+                // Handle buckets that have never had CORS configured
+                if error
+                    .as_service_error()
+                    .and_then(|error| error.meta().code())
+                    .is_some_and(|code| code == "NoSuchCORSConfiguration")
+                {
+                    return Ok(Some(Vec::new()));
+                }
+
+                // Handle "NotImplemented" errors (minio does not have CORS support)
+                if error
+                    .raw_response()
+                    // (501 Not Implemented)
+                    .is_some_and(|response| response.status().as_u16() == 501)
+                {
+                    tracing::warn!("storage s3 backend does not support GetBucketCors");
+                    return Ok(None);
+                }
+
+                return Err(error).context("failed to get bucket cors");
+            }
+        };
+
+        let mut origins: Vec<String> = Vec::new();
+
+        for origin in output
+            .cors_rules()
+            .iter()
+            .flat_map(|rule| rule.allowed_origins())
+        {
+            if !origins.contains(origin) {
+                origins.push(origin.clone());
+            }
+        }
+
+        Ok(Some(origins))
+    }
+
+    /// Walk every object within the bucket to determine its usage, keeping track
+    /// of the `largest_limit` largest objects
+    ///
+    /// Progress is reported through a progress bar as each page of objects is listed
+    pub async fn get_bucket_usage(
+        &self,
+        bucket_name: &str,
+        largest_limit: usize,
+    ) -> eyre::Result<BucketUsage> {
+        let span = tracing::info_span!("bucket_usage", bucket = bucket_name);
+        span.pb_set_style(
+            &ProgressStyle::with_template("{spinner} {span_fields} {pos} objects ({msg})")
+                .context("invalid progress style")?,
+        );
+
+        async {
+            let mut usage = BucketUsage::default();
+
+            // Min-heap of the largest objects, the smallest is replaced once full
+            let mut largest: BinaryHeap<Reverse<(u64, String)>> =
+                BinaryHeap::with_capacity(largest_limit + 1);
+
+            let mut pages = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket_name)
+                .into_paginator()
+                .send();
+
+            while let Some(page) = pages.next().await {
+                let page = page.context("failed to list bucket objects")?;
+                let span = tracing::Span::current();
+
+                for object in page.contents() {
+                    let size = object.size().unwrap_or_default().max(0) as u64;
+
+                    usage.object_count += 1;
+                    usage.total_bytes += size;
+
+                    if largest_limit > 0
+                        && (largest.len() < largest_limit
+                            || largest
+                                .peek()
+                                .is_some_and(|Reverse((smallest, _))| size > *smallest))
+                    {
+                        largest.push(Reverse((
+                            size,
+                            object.key().unwrap_or_default().to_string(),
+                        )));
+                        if largest.len() > largest_limit {
+                            largest.pop();
+                        }
+                    }
+
+                    span.pb_inc(1);
+                }
+
+                span.pb_set_message(&format_bytes(usage.total_bytes));
+            }
+
+            // Sorted ascending by the reversed order, largest first
+            usage.largest_objects = largest
+                .into_sorted_vec()
+                .into_iter()
+                .map(|Reverse((size, key))| StorageObject {
+                    key,
+                    size,
+                    etag: None,
+                })
+                .collect();
+
+            Ok(usage)
+        }
+        .instrument(span.clone())
+        .await
+    }
+
+    /// Check if the bucket exists
+    pub async fn bucket_exists(&self, bucket_name: &str) -> eyre::Result<bool> {
+        match self.client.head_bucket().bucket(bucket_name).send().await {
+            Ok(_) => Ok(true),
+            Err(error) => {
+                if error
+                    .as_service_error()
+                    .is_some_and(|error| error.is_not_found())
+                {
+                    return Ok(false);
+                }
+
+                Err(error).context("failed to check bucket")
+            }
+        }
+    }
+
+    /// List the names of every bucket
+    pub async fn list_buckets(&self) -> eyre::Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut pages = self.client.list_buckets().into_paginator().send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.context("failed to list buckets")?;
+
+            names.extend(
+                page.buckets()
+                    .iter()
+                    .filter_map(|bucket| bucket.name())
+                    .map(|name| name.to_string()),
+            );
+        }
+
+        Ok(names)
+    }
+
+    /// List every object within the bucket
+    pub async fn list_objects(&self, bucket_name: &str) -> eyre::Result<Vec<StorageObject>> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(bucket_name)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.context("failed to list bucket objects")?;
+
+            objects.extend(page.contents().iter().map(|object| StorageObject {
+                key: object.key().unwrap_or_default().to_string(),
+                size: object.size().unwrap_or_default().max(0) as u64,
+                etag: object.e_tag().map(|etag| etag.to_string()),
+            }));
+        }
+
+        Ok(objects)
+    }
+
+    /// Copy the event notification configuration of one bucket to another
+    pub async fn copy_bucket_notifications(
+        &self,
+        from_bucket: &str,
+        to_bucket: &str,
+    ) -> eyre::Result<()> {
+        let output = self
+            .client
+            .get_bucket_notification_configuration()
+            .bucket(from_bucket)
+            .send()
+            .await
+            .context("failed to get bucket notifications")?;
+
+        if output.queue_configurations().is_empty()
+            && output.topic_configurations().is_empty()
+            && output.lambda_function_configurations().is_empty()
+            && output.event_bridge_configuration().is_none()
+        {
+            return Ok(());
+        }
+
+        self.client
+            .put_bucket_notification_configuration()
+            .bucket(to_bucket)
+            .notification_configuration(
+                NotificationConfiguration::builder()
+                    .set_queue_configurations(Some(output.queue_configurations().to_vec()))
+                    .set_topic_configurations(Some(output.topic_configurations().to_vec()))
+                    .set_lambda_function_configurations(Some(
+                        output.lambda_function_configurations().to_vec(),
+                    ))
+                    .set_event_bridge_configuration(output.event_bridge_configuration().cloned())
+                    .build(),
+            )
+            .send()
+            .await
+            .context("failed to put bucket notifications")?;
+
+        Ok(())
+    }
+
+    /// Copy every object from one bucket to another
+    ///
+    /// Objects that are already present in the destination bucket with a matching
+    /// size and ETag are skipped, allowing an interrupted copy to be resumed. When
+    /// `only_missing` is set, objects that are present with a different ETag are
+    /// left as is rather than being replaced
+    pub async fn copy_bucket_objects(
+        &self,
+        from_bucket: &str,
+        to_bucket: &str,
+        only_missing: bool,
+    ) -> eyre::Result<CopyObjectsOutcome> {
+        let objects = self.list_objects(from_bucket).await?;
+        let existing_objects: HashMap<String, StorageObject> = self
+            .list_objects(to_bucket)
+            .await?
+            .into_iter()
+            .map(|object| (object.key.clone(), object))
+            .collect();
+
+        let span = tracing::info_span!("copy_objects", from_bucket, to_bucket);
+        span.pb_set_style(
+            &ProgressStyle::with_template("{spinner} {span_fields} [{bar:40}] {pos}/{len} objects")
+                .context("invalid progress style")?,
+        );
+        span.pb_set_length(objects.len() as u64);
+
+        async {
+            let span = tracing::Span::current();
+            let mut outcome = CopyObjectsOutcome::default();
+
+            for object in &objects {
+                let existing = existing_objects.get(&object.key);
+                let skip = match existing {
+                    Some(_) if only_missing => true,
+                    Some(existing) => object.is_same_content(existing),
+                    None => false,
+                };
+
+                if skip {
+                    outcome.skipped_objects += 1;
+                } else {
+                    self.copy_object(from_bucket, to_bucket, object).await?;
+                    outcome.copied_objects += 1;
+                    outcome.copied_bytes += object.size;
+                }
+
+                span.pb_inc(1);
+            }
+
+            Ok(outcome)
+        }
+        .instrument(span.clone())
+        .await
+    }
+
+    /// Copy an `object` from one bucket to another, verifying that the
+    /// copied object matches the source object
+    ///
+    /// Objects that were uploaded in multiple parts or are too large for a
+    /// single copy are copied part by part using the same part sizes as the
+    /// source, so the ETag of the copy matches the source ETag
+    pub async fn copy_object(
+        &self,
+        from_bucket: &str,
+        to_bucket: &str,
+        object: &StorageObject,
+    ) -> eyre::Result<()> {
+        let etag = object
+            .etag
+            .as_deref()
+            .with_context(|| format!("object {} is missing its ETag", object.key))?;
+
+        let copy_source = format!(
+            "{from_bucket}/{}",
+            utf8_percent_encode(&object.key, COPY_SOURCE_ENCODE_SET)
+        );
+
+        if is_multipart_etag(etag) || object.size > MAX_COPY_OBJECT_SIZE {
+            self.copy_object_parts(from_bucket, to_bucket, object, etag, &copy_source)
+                .await?;
+        } else {
+            self.client
+                .copy_object()
+                .copy_source(copy_source)
+                // Ensure the listed version of the object is the one copied
+                .copy_source_if_match(etag)
+                .bucket(to_bucket)
+                .key(&object.key)
+                .metadata_directive(MetadataDirective::Copy)
+                .send()
+                .await
+                .with_context(|| format!("failed to copy object {}", object.key))?;
+        }
+
+        let copied = self
+            .client
+            .head_object()
+            .bucket(to_bucket)
+            .key(&object.key)
+            .send()
+            .await
+            .with_context(|| format!("failed to verify copied object {}", object.key))?;
+
+        let copied = StorageObject {
+            key: object.key.clone(),
+            size: copied.content_length().unwrap_or_default().max(0) as u64,
+            etag: copied.e_tag().map(|etag| etag.to_string()),
+        };
+
+        if !object.is_same_content(&copied) {
+            eyre::bail!(
+                "copied object {} does not match the source (expected {} bytes with ETag {etag}, got {} bytes with ETag {})",
+                object.key,
+                object.size,
+                copied.size,
+                copied.etag.as_deref().unwrap_or_default()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Copy an object using a multipart upload, each part of the source object
+    /// is copied as a part of the same size
+    async fn copy_object_parts(
+        &self,
+        from_bucket: &str,
+        to_bucket: &str,
+        object: &StorageObject,
+        etag: &str,
+        copy_source: &str,
+    ) -> eyre::Result<()> {
+        let source = self
+            .client
+            .head_object()
+            .bucket(from_bucket)
+            .key(&object.key)
+            .if_match(etag)
+            .part_number(1)
+            .send()
+            .await
+            .with_context(|| format!("failed to get object {}", object.key))?;
+
+        // Part sizes of the source object, objects that were not uploaded
+        // in parts are split into parts of the largest size allowed
+        let part_sizes: Vec<u64> = match source.parts_count() {
+            Some(parts_count) if parts_count > 1 => {
+                let mut part_sizes =
+                    vec![source.content_length().unwrap_or_default().max(0) as u64];
+
+                for part_number in 2..=parts_count {
+                    let part = self
+                        .client
+                        .head_object()
+                        .bucket(from_bucket)
+                        .key(&object.key)
+                        .if_match(etag)
+                        .part_number(part_number)
+                        .send()
+                        .await
+                        .with_context(|| format!("failed to get object {} part", object.key))?;
+
+                    part_sizes.push(part.content_length().unwrap_or_default().max(0) as u64);
+                }
+
+                part_sizes
+            }
+            _ => {
+                let mut part_sizes = Vec::new();
+                let mut remaining = object.size;
+                while remaining > 0 {
+                    let part_size = remaining.min(MAX_COPY_OBJECT_SIZE);
+                    part_sizes.push(part_size);
+                    remaining -= part_size;
+                }
+                part_sizes
+            }
+        };
+
+        let upload = self
+            .client
+            .create_multipart_upload()
+            .bucket(to_bucket)
+            .key(&object.key)
+            .set_cache_control(source.cache_control().map(str::to_string))
+            .set_content_disposition(source.content_disposition().map(str::to_string))
+            .set_content_encoding(source.content_encoding().map(str::to_string))
+            .set_content_language(source.content_language().map(str::to_string))
+            .set_content_type(source.content_type().map(str::to_string))
+            .set_metadata(source.metadata().cloned())
+            .send()
+            .await
+            .with_context(|| format!("failed to start copying object {}", object.key))?;
+
+        let upload_id = upload
+            .upload_id()
+            .context("multipart upload is missing its upload id")?;
+
+        let result = async {
+            let mut parts = Vec::with_capacity(part_sizes.len());
+            let mut start = 0;
+
+            for (part_number, part_size) in (1..).zip(part_sizes) {
+                let end = start + part_size - 1;
+
+                let output = self
+                    .client
+                    .upload_part_copy()
+                    .bucket(to_bucket)
+                    .key(&object.key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .copy_source(copy_source)
+                    .copy_source_if_match(etag)
+                    .copy_source_range(format!("bytes={start}-{end}"))
+                    .send()
+                    .await
+                    .with_context(|| {
+                        format!("failed to copy part {part_number} of object {}", object.key)
+                    })?;
+
+                parts.push(
+                    CompletedPart::builder()
+                        .part_number(part_number)
+                        .set_e_tag(
+                            output
+                                .copy_part_result()
+                                .and_then(|result| result.e_tag())
+                                .map(str::to_string),
+                        )
+                        .build(),
+                );
+
+                start = end + 1;
+            }
+
+            self.client
+                .complete_multipart_upload()
+                .bucket(to_bucket)
+                .key(&object.key)
+                .upload_id(upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts))
+                        .build(),
+                )
+                .send()
+                .await
+                .with_context(|| format!("failed to complete copying object {}", object.key))?;
+
+            Ok(())
+        }
+        .await;
+
+        if result.is_err()
+            && let Err(error) = self
+                .client
+                .abort_multipart_upload()
+                .bucket(to_bucket)
+                .key(&object.key)
+                .upload_id(upload_id)
+                .send()
+                .await
+        {
+            tracing::error!(?error, "failed to abort multipart object copy");
+        }
+
+        result
+    }
+}
+
+/// Usage details for a storage bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BucketUsage {
+    /// Number of objects stored in the bucket
+    pub object_count: u64,
+    /// Total size in bytes of every object in the bucket
+    pub total_bytes: u64,
+    /// Largest objects in the bucket (Largest first)
+    pub largest_objects: Vec<StorageObject>,
+}
+
+/// Outcome from copying the objects of a bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CopyObjectsOutcome {
+    /// Number of objects that were copied
+    pub copied_objects: u64,
+    /// Total size in bytes of the copied objects
+    pub copied_bytes: u64,
+    /// Number of objects that were already present
+    pub skipped_objects: u64,
+}
+
+/// Object stored within a bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageObject {
+    /// Key of the object
+    pub key: String,
+    /// Size of the object in bytes
+    pub size: u64,
+    /// ETag of the object
+    #[serde(skip)]
+    pub etag: Option<String>,
+}
+
+impl StorageObject {
+    /// Whether the `other` object has the same size and ETag, objects
+    /// without an ETag are never considered the same
+    pub fn is_same_content(&self, other: &StorageObject) -> bool {
+        self.size == other.size && self.etag.is_some() && self.etag == other.etag
+    }
+}
+
+/// Whether the ETag is for an object uploaded in multiple parts ("<md5>-<parts>")
+fn is_multipart_etag(etag: &str) -> bool {
+    etag.trim_matches('"').contains('-')
+}
+
+/// Format a number of bytes in a human readable form
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+/// Change to apply to the allowed CORS origins of a bucket
+#[derive(Debug, Clone)]
+pub enum CorsOriginsChange {
+    /// Replace the existing origins
+    Set(Vec<String>),
+    /// Add to the existing origins
+    Add(Vec<String>),
+    /// Remove from the existing origins
+    Remove(Vec<String>),
+}
+
+impl CorsOriginsChange {
+    /// Apply the change to the `previous` origins
+    pub fn apply(&self, previous: &[String]) -> Vec<String> {
+        match self {
+            CorsOriginsChange::Set(origins) => origins.clone(),
+            CorsOriginsChange::Add(origins) => {
+                let mut current = previous.to_vec();
+                for origin in origins {
+                    if !current.contains(origin) {
+                        current.push(origin.clone());
+                    }
+                }
+                current
+            }
+            CorsOriginsChange::Remove(origins) => previous
+                .iter()
+                .filter(|origin| !origins.contains(origin))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Apply the change to the bucket of the `storage` layer, the bucket is
+    /// only updated when the change modifies the existing origins
+    ///
+    /// Adding or removing origins fails when the existing origins cannot be read
+    /// from the storage backend, unless `force` is set in which case the bucket
+    /// is treated as having no existing origins
+    pub async fn apply_to_bucket(
+        &self,
+        storage: &StorageLayer,
+        client: &StorageClient,
+        force: bool,
+    ) -> eyre::Result<CorsOriginsDiff> {
+        let previous = match client
+            .get_bucket_cors_origins(&storage.bucket_name())
+            .await?
+        {
+            Some(previous) => previous,
+            None if force || matches!(self, CorsOriginsChange::Set(_)) => Vec::new(),
+            None => eyre::bail!(
+                "storage backend does not support reading the existing CORS origins, use --force to replace them"
+            ),
+        };
+
+        let diff = CorsOriginsDiff::new(previous.clone(), self.apply(&previous));
+
+        if diff.is_changed() {
+            storage
+                .set_bucket_cors_origins(diff.current.clone())
+                .await
+                .context("failed to set bucket cors")?;
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Change made to the allowed CORS origins of a bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct CorsOriginsDiff {
+    /// Origins before the change
+    pub previous: Vec<String>,
+    /// Origins after the change
+    pub current: Vec<String>,
+    /// Origins that were added
+    pub added: Vec<String>,
+    /// Origins that were removed
+    pub removed: Vec<String>,
+}
+
+impl CorsOriginsDiff {
+    pub fn new(previous: Vec<String>, current: Vec<String>) -> Self {
+        let added = current
+            .iter()
+            .filter(|origin| !previous.contains(origin))
+            .cloned()
+            .collect();
+        let removed = previous
+            .iter()
+            .filter(|origin| !current.contains(origin))
+            .cloned()
+            .collect();
+
+        Self {
+            previous,
+            current,
+            added,
+            removed,
+        }
+    }
+
+    /// Whether the change modified the origins
+    pub fn is_changed(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Print the diff in a human readable format
+    pub fn print(&self) {
+        for origin in &self.previous {
+            if self.removed.contains(origin) {
+                println!("- {origin}");
+            } else {
+                println!("  {origin}");
+            }
+        }
+
+        for origin in &self.added {
+            println!("+ {origin}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CorsOriginsChange, CorsOriginsDiff};
+
+    fn origins(origins: &[&str]) -> Vec<String> {
+        origins.iter().map(|origin| origin.to_string()).collect()
+    }
+
+    /// Tests that setting origins replaces the existing origins
+    #[test]
+    fn test_cors_set() {
+        let change = CorsOriginsChange::Set(origins(&["https://b.com"]));
+        let current = change.apply(&origins(&["https://a.com"]));
+        assert_eq!(current, origins(&["https://b.com"]));
+    }
+
+    /// Tests that adding origins keeps the existing origins and skips duplicates
+    #[test]
+    fn test_cors_add() {
+        let change = CorsOriginsChange::Add(origins(&["https://a.com", "https://b.com"]));
+        let current = change.apply(&origins(&["https://a.com", "https://c.com"]));
+        assert_eq!(
+            current,
+            origins(&["https://a.com", "https://c.com", "https://b.com"])
+        );
+    }
+
+    /// Tests that removing origins only removes the provided origins
+    #[test]
+    fn test_cors_remove() {
+        let change = CorsOriginsChange::Remove(origins(&["https://a.com", "https://d.com"]));
+        let current = change.apply(&origins(&["https://a.com", "https://c.com"]));
+        assert_eq!(current, origins(&["https://c.com"]));
+    }
+
+    /// Tests that the diff reports the added and removed origins
+    #[test]
+    fn test_cors_diff() {
+        let diff = CorsOriginsDiff::new(
+            origins(&["https://a.com", "https://b.com"]),
+            origins(&["https://b.com", "https://c.com"]),
+        );
+        assert_eq!(diff.added, origins(&["https://c.com"]));
+        assert_eq!(diff.removed, origins(&["https://a.com"]));
+        assert!(diff.is_changed());
+    }
+
+    /// Tests that reordering or re-adding existing origins is not a change
+    #[test]
+    fn test_cors_diff_unchanged() {
+        let previous = origins(&["https://a.com", "https://b.com"]);
+        let current = CorsOriginsChange::Add(origins(&["https://b.com"])).apply(&previous);
+        let diff = CorsOriginsDiff::new(previous, current);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(!diff.is_changed());
+    }
+}
@@ -0,0 +1,303 @@
+//! Detailed state of a tenant and its backing resources
+
+use crate::{search::SearchClient, storage::StorageClient, tenants::TenantAuth};
+use docbox_management::{
+    core::{aws::SqsClient, secrets::SecretManager},
+    database::{
+        DatabaseProvider, DbPool, ROOT_DATABASE_NAME, close_pool_on_drop,
+        models::{tenant::Tenant, tenant_migration::TenantMigration},
+        sqlx,
+    },
+};
+use eyre::Context;
+use serde::Serialize;
+
+/// Details of a tenant
+#[derive(Serialize)]
+pub struct TenantDetail {
+    #[serde(flatten)]
+    pub tenant: Tenant,
+    pub db_auth: TenantAuth,
+    pub applied_migrations: Vec<TenantMigration>,
+    /// Details checked against the live backends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live: Option<TenantLiveDetail>,
+}
+
+/// Details of a tenant that are checked against the live backends
+#[derive(Serialize)]
+pub struct TenantLiveDetail {
+    pub cors_origins: LiveCheck<Vec<String>>,
+    pub search_document_count: LiveCheck<u64>,
+    pub database_exists: LiveCheck<bool>,
+    pub bucket_exists: LiveCheck<bool>,
+    pub search_index_exists: LiveCheck<bool>,
+    /// Only checked for tenants using secret based database authentication
+    pub secret_exists: Option<LiveCheck<bool>>,
+    /// Only checked for tenants with an event queue
+    pub event_queue_exists: Option<LiveCheck<bool>>,
+}
+
+/// Outcome of checking a value against a live backend, both the value
+/// and error are empty when the check is not supported by the backend
+#[derive(Serialize)]
+pub struct LiveCheck<T> {
+    pub value: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> LiveCheck<T> {
+    fn value(value: T) -> Self {
+        Self {
+            value: Some(value),
+            error: None,
+        }
+    }
+
+    fn failed(error: String) -> Self {
+        Self {
+            value: None,
+            error: Some(error),
+        }
+    }
+
+    fn unsupported() -> Self {
+        Self {
+            value: None,
+            error: None,
+        }
+    }
+
+    /// Text for human readable output, using `format` for the value
+    pub fn display(&self, format: impl Fn(&T) -> String) -> String {
+        match (&self.value, &self.error) {
+            (_, Some(error)) => format!("Failed: {error}"),
+            (Some(value), None) => format(value),
+            (None, None) => "N/A".to_string(),
+        }
+    }
+}
+
+impl<T> From<eyre::Result<T>> for LiveCheck<T> {
+    fn from(result: eyre::Result<T>) -> Self {
+        match result {
+            Ok(value) => Self::value(value),
+            Err(error) => Self::failed(format!("{error:#}")),
+        }
+    }
+}
+
+impl TenantDetail {
+    /// Labelled values of the detail for human readable output
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let mut rows = tenant_rows(&self.tenant);
+
+        let applied_migrations = self
+            .applied_migrations
+            .iter()
+            .map(|migration| migration.name.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        rows.push(("Applied Migrations", applied_migrations));
+
+        if let Some(live) = self.live.as_ref() {
+            let exists = |exists: &bool| if *exists { "Yes" } else { "No" }.to_string();
+
+            rows.push((
+                "Storage CORS Origins",
+                live.cors_origins.display(|origins| origins.join("\n")),
+            ));
+            rows.push((
+                "Search Documents",
+                live.search_document_count.display(u64::to_string),
+            ));
+            rows.push(("Database Exists", live.database_exists.display(exists)));
+            rows.push(("Bucket Exists", live.bucket_exists.display(exists)));
+            rows.push((
+                "Search Index Exists",
+                live.search_index_exists.display(exists),
+            ));
+
+            if let Some(secret_exists) = live.secret_exists.as_ref() {
+                rows.push(("DB Secret Exists", secret_exists.display(exists)));
+            }
+
+            if let Some(event_queue_exists) = live.event_queue_exists.as_ref() {
+                rows.push(("Event Queue Exists", event_queue_exists.display(exists)));
+            }
+        }
+
+        rows
+    }
+}
+
+/// Labelled values of a tenant for human readable output
+pub fn tenant_rows(tenant: &Tenant) -> Vec<(&'static str, String)> {
+    vec![
+        ("ID", tenant.id.to_string()),
+        ("Name", tenant.name.clone()),
+        ("Env", tenant.env.clone()),
+        ("DB Name", tenant.db_name.clone()),
+        ("DB Auth", TenantAuth::from_tenant(tenant).to_string()),
+        (
+            "DB Secret Name",
+            tenant.db_secret_name.clone().unwrap_or_default(),
+        ),
+        (
+            "DB IAM User Name",
+            tenant.db_iam_user_name.clone().unwrap_or_default(),
+        ),
+        ("Storage Bucket Name", tenant.s3_name.clone()),
+        ("Search Index Name", tenant.os_index_name.clone()),
+        (
+            "Event Queue URL",
+            tenant.event_queue_url.clone().unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Get the details of a `tenant`
+pub async fn get_tenant_detail(
+    db_provider: &impl DatabaseProvider,
+    tenant: Tenant,
+) -> eyre::Result<TenantDetail> {
+    let root_db = db_provider
+        .connect(ROOT_DATABASE_NAME)
+        .await
+        .context("failed to connect to root db")?;
+
+    let _guard = close_pool_on_drop(&root_db);
+
+    let mut applied_migrations = TenantMigration::find_by_tenant(&root_db, tenant.id, &tenant.env)
+        .await
+        .context("failed to get tenant migrations")?;
+
+    applied_migrations.sort_by_key(|migration| migration.applied_at);
+
+    Ok(TenantDetail {
+        db_auth: TenantAuth::from_tenant(&tenant),
+        tenant,
+        applied_migrations,
+        live: None,
+    })
+}
+
+/// Check the state of the tenant resources against the live backends
+pub async fn get_tenant_live_detail(
+    db_provider: &impl DatabaseProvider,
+    secrets: &SecretManager,
+    storage_client: &StorageClient,
+    search_client: eyre::Result<&SearchClient>,
+    sqs_client: &SqsClient,
+    tenant: &Tenant,
+) -> eyre::Result<TenantLiveDetail> {
+    let root_db = db_provider
+        .connect(ROOT_DATABASE_NAME)
+        .await
+        .context("failed to connect to root db")?;
+
+    let _guard = close_pool_on_drop(&root_db);
+
+    let search_index = async {
+        let result = match search_client {
+            // Database search indexes are part of the tenant database
+            Ok(SearchClient::Database) => {
+                return (LiveCheck::unsupported(), LiveCheck::unsupported());
+            }
+            Ok(search_client) => search_client
+                .get_index_document_count(&tenant.os_index_name)
+                .await
+                .map_err(|error| format!("{error:#}")),
+            Err(error) => Err(format!("{error:#}")),
+        };
+
+        match result {
+            Ok(Some(count)) => (LiveCheck::value(count), LiveCheck::value(true)),
+            Ok(None) => (LiveCheck::unsupported(), LiveCheck::value(false)),
+            Err(error) => (LiveCheck::failed(error.clone()), LiveCheck::failed(error)),
+        }
+    };
+
+    let secret_exists = async {
+        match tenant.db_secret_name.as_deref() {
+            Some(secret_name) => Some(
+                secrets
+                    .has_secret(secret_name)
+                    .await
+                    .context("failed to check secret")
+                    .into(),
+            ),
+            None => None,
+        }
+    };
+
+    let event_queue_exists = async {
+        match tenant.event_queue_url.as_deref() {
+            Some(queue_url) => Some(event_queue_exists(sqs_client, queue_url).await.into()),
+            None => None,
+        }
+    };
+
+    let (
+        cors_origins,
+        (search_document_count, search_index_exists),
+        database_exists,
+        bucket_exists,
+        secret_exists,
+        event_queue_exists,
+    ) = tokio::join!(
+        storage_client.get_bucket_cors_origins(&tenant.s3_name),
+        search_index,
+        database_exists(&root_db, &tenant.db_name),
+        storage_client.bucket_exists(&tenant.s3_name),
+        secret_exists,
+        event_queue_exists,
+    );
+
+    Ok(TenantLiveDetail {
+        cors_origins: match cors_origins {
+            Ok(Some(origins)) => LiveCheck::value(origins),
+            Ok(None) => LiveCheck::unsupported(),
+            Err(error) => LiveCheck::failed(format!("{error:#}")),
+        },
+        search_document_count,
+        database_exists: database_exists.into(),
+        bucket_exists: bucket_exists.into(),
+        search_index_exists,
+        secret_exists,
+        event_queue_exists,
+    })
+}
+
+async fn database_exists(db: &DbPool, db_name: &str) -> eyre::Result<bool> {
+    let exists: bool =
+        sqlx::query_scalar(r#"SELECT EXISTS(SELECT 1 FROM "pg_database" WHERE "datname" = $1)"#)
+            .bind(db_name)
+            .fetch_one(db)
+            .await
+            .context("failed to check database")?;
+
+    Ok(exists)
+}
+
+async fn event_queue_exists(sqs_client: &SqsClient, queue_url: &str) -> eyre::Result<bool> {
+    match sqs_client
+        .get_queue_attributes()
+        .queue_url(queue_url)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(error) => {
+            if error
+                .as_service_error()
+                .is_some_and(|error| error.is_queue_does_not_exist())
+            {
+                return Ok(false);
+            }
+
+            Err(error).context("failed to check event queue")
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Searching for tenants within the root database
+
+use crate::{columns::TenantColumn, completion::complete_env};
+use clap::ValueEnum;
+use clap_complete::ArgValueCandidates;
+use docbox_management::database::{
+    DatabaseProvider, ROOT_DATABASE_NAME, close_pool_on_drop,
+    models::tenant::Tenant,
+    sqlx::{Postgres, QueryBuilder},
+};
+use eyre::Context;
+use serde::Serialize;
+
+/// Database authentication mode used by a tenant
+#[derive(ValueEnum, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantAuth {
+    Iam,
+    Secret,
+}
+
+impl TenantAuth {
+    pub fn from_tenant(tenant: &Tenant) -> Self {
+        if tenant.db_iam_user_name.is_some() {
+            Self::Iam
+        } else {
+            Self::Secret
+        }
+    }
+}
+
+impl std::fmt::Display for TenantAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TenantAuth::Iam => "IAM",
+            TenantAuth::Secret => "Secret",
+        })
+    }
+}
+
+/// Resource owned by a tenant
+#[derive(Clone)]
+pub enum TenantResource {
+    /// Name of the tenant database
+    Database(String),
+    /// Name of the tenant storage bucket
+    Bucket(String),
+    /// Name of the tenant search index
+    SearchIndex(String),
+    /// Name of the tenant database secret
+    Secret(String),
+}
+
+/// Filters for searching tenants
+#[derive(clap::Args, Clone, Default)]
+pub struct TenantFilter {
+    /// Environment to filter to
+    #[arg(short, long, add = ArgValueCandidates::new(complete_env))]
+    pub env: Option<String>,
+
+    /// Case insensitive name to filter to, matches any part of the name unless
+    /// the name contains glob wildcards (* or ?)
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Start of the tenant ID to filter to
+    #[arg(long)]
+    pub id_prefix: Option<String>,
+
+    /// Database authentication mode to filter to
+    #[arg(long, value_enum)]
+    pub auth: Option<TenantAuth>,
+
+    /// Only include tenants with an event queue
+    #[arg(long)]
+    pub has_event_queue: bool,
+
+    /// Maximum number of tenants to return
+    #[arg(long)]
+    pub limit: Option<u32>,
+
+    /// Number of tenants to skip
+    #[arg(long)]
+    pub offset: Option<u32>,
+
+    /// Resource the tenant must own, used when finding tenants by resource
+    #[arg(skip)]
+    pub resource: Option<TenantResource>,
+}
+
+impl TenantFilter {
+    /// Whether the filter matches every tenant
+    pub fn is_unfiltered(&self) -> bool {
+        self.env.is_none()
+            && self.name.is_none()
+            && self.id_prefix.is_none()
+            && self.auth.is_none()
+            && !self.has_event_queue
+            && self.limit.is_none()
+            && self.offset.is_none()
+            && self.resource.is_none()
+    }
+}
+
+/// Find the tenants matching the `filter` sorted by the `sort_by` column
+pub async fn find_tenants(
+    db_provider: &impl DatabaseProvider,
+    filter: &TenantFilter,
+    sort_by: Option<TenantColumn>,
+) -> eyre::Result<Vec<Tenant>> {
+    let db = db_provider
+        .connect(ROOT_DATABASE_NAME)
+        .await
+        .context("failed to connect to root db")?;
+
+    let _guard = close_pool_on_drop(&db);
+
+    let mut query: QueryBuilder<Postgres> =
+        QueryBuilder::new(r#"SELECT * FROM "docbox_tenants" WHERE TRUE"#);
+
+    if let Some(env) = filter.env.as_ref() {
+        query.push(r#" AND "env" = "#).push_bind(env);
+    }
+
+    if let Some(name) = filter.name.as_deref() {
+        let pattern = if name.contains(['*', '?']) {
+            glob_to_like(name)
+        } else {
+            format!("%{}%", escape_like(name))
+        };
+
+        query.push(r#" AND "name" ILIKE "#).push_bind(pattern);
+    }
+
+    if let Some(id_prefix) = filter.id_prefix.as_deref() {
+        query
+            .push(r#" AND "id"::TEXT LIKE "#)
+            .push_bind(format!("{}%", escape_like(&id_prefix.to_lowercase())));
+    }
+
+    match filter.auth {
+        Some(TenantAuth::Iam) => {
+            query.push(r#" AND "db_iam_user_name" IS NOT NULL"#);
+        }
+        Some(TenantAuth::Secret) => {
+            query.push(r#" AND "db_iam_user_name" IS NULL"#);
+        }
+        None => {}
+    }
+
+    match filter.resource.as_ref() {
+        Some(TenantResource::Database(name)) => {
+            query.push(r#" AND "db_name" = "#).push_bind(name);
+        }
+        Some(TenantResource::Bucket(name)) => {
+            query.push(r#" AND "s3_name" = "#).push_bind(name);
+        }
+        Some(TenantResource::SearchIndex(name)) => {
+            query.push(r#" AND "os_index_name" = "#).push_bind(name);
+        }
+        Some(TenantResource::Secret(name)) => {
+            query.push(r#" AND "db_secret_name" = "#).push_bind(name);
+        }
+        None => {}
+    }
+
+    if filter.has_event_queue {
+        query.push(r#" AND "event_queue_url" IS NOT NULL"#);
+    }
+
+    // Column keys match the tenant table column names
+    let sort_column = sort_by.unwrap_or(TenantColumn::Name).key();
+    query.push(format!(r#" ORDER BY "{sort_column}", "id""#));
+
+    if let Some(limit) = filter.limit {
+        query.push(" LIMIT ").push_bind(i64::from(limit));
+    }
+
+    if let Some(offset) = filter.offset {
+        query.push(" OFFSET ").push_bind(i64::from(offset));
+    }
+
+    let tenants = query
+        .build_query_as::<Tenant>()
+        .fetch_all(&db)
+        .await
+        .context("failed to query tenants")?;
+
+    Ok(tenants)
+}
+
+/// Escape the LIKE wildcards within `value`
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if matches!(char, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
+
+/// Convert a glob pattern into a LIKE pattern
+fn glob_to_like(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len());
+    for char in glob.chars() {
+        match char {
+            '*' => pattern.push('%'),
+            '?' => pattern.push('_'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(char);
+            }
+            char => pattern.push(char),
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_like, glob_to_like};
+
+    /// Tests that LIKE wildcards and the escape character are escaped
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("tenant"), "tenant");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("my_tenant"), "my\\_tenant");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+        assert_eq!(escape_like("*?"), "*?");
+    }
+
+    /// Tests that glob wildcards become LIKE wildcards
+    #[test]
+    fn test_glob_to_like() {
+        assert_eq!(glob_to_like("acme*"), "acme%");
+        assert_eq!(glob_to_like("*-prod"), "%-prod");
+        assert_eq!(glob_to_like("tenant-?"), "tenant-_");
+        assert_eq!(glob_to_like("tenant"), "tenant");
+    }
+
+    /// Tests that LIKE wildcards within a glob are matched literally
+    #[test]
+    fn test_glob_to_like_escapes() {
+        assert_eq!(glob_to_like("my_tenant*"), "my\\_tenant%");
+        assert_eq!(glob_to_like("100%?"), "100\\%_");
+        assert_eq!(glob_to_like("a\\*"), "a\\\\%");
+    }
+}
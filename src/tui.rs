@@ -0,0 +1,779 @@
+//! Interactive terminal interface for browsing tenants and running
+//! operations against them
+
+use crate::{
+    backends::Backends,
+    storage::CorsOriginsChange,
+    tenant_detail::{TenantDetail, get_tenant_detail, get_tenant_live_detail, tenant_rows},
+};
+use docbox_management::{
+    config::ServerConfigData,
+    core::tenant::{
+        rebuild_tenant_index::rebuild_tenant_index, tenant_options_ext::TenantOptionsExt,
+    },
+    database::{DatabaseProvider, close_pool_on_drop, models::tenant::Tenant},
+    tenant::{
+        flush_tenant_cache::flush_tenant_cache,
+        get_tenants::get_tenants,
+        migrate_tenants::{MigrateTenantsConfig, migrate_tenants},
+    },
+};
+use eyre::Context;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        Block, BorderType, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+    },
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// How often the event reader checks whether it should stop
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Backends and configuration used by the interface operations
+#[derive(Clone, Copy)]
+pub struct TuiContext<'a> {
+    pub config: &'a ServerConfigData,
+    pub backends: &'a Backends<'a>,
+}
+
+/// Run the interface until the user quits
+pub async fn run_tui(ctx: TuiContext<'_>) -> eyre::Result<()> {
+    let mut terminal = ratatui::init();
+    let mut events = EventReader::spawn();
+    let result = run_app(&mut terminal, ctx, &mut events.events).await;
+    // Stop reading before restoring so input is left for the parent terminal
+    events.stop();
+    ratatui::restore();
+    result
+}
+
+/// Operation against the selected tenant, each requires confirmation
+#[derive(Clone)]
+enum Action {
+    FlushCache,
+    AddCorsOrigin(String),
+    RemoveCorsOrigin(String),
+    RebuildIndex,
+    Migrate,
+}
+
+impl Action {
+    /// Description of the action shown when confirming
+    fn describe(&self, tenant: &Tenant) -> String {
+        let tenant = format!("{} ({})", tenant.name, tenant.env);
+        match self {
+            Action::FlushCache => "Flush the API server tenant cache".to_string(),
+            Action::AddCorsOrigin(origin) => {
+                format!("Add allowed CORS origin {origin} to {tenant}")
+            }
+            Action::RemoveCorsOrigin(origin) => {
+                format!("Remove allowed CORS origin {origin} from {tenant}")
+            }
+            Action::RebuildIndex => format!("Rebuild the search index of {tenant}"),
+            Action::Migrate => format!("Apply pending migrations to {tenant}"),
+        }
+    }
+}
+
+/// Action that requires a text value before it can be confirmed
+#[derive(Clone, Copy)]
+enum InputAction {
+    AddCorsOrigin,
+    RemoveCorsOrigin,
+}
+
+impl InputAction {
+    fn title(&self) -> &'static str {
+        match self {
+            InputAction::AddCorsOrigin => "Add allowed CORS origin",
+            InputAction::RemoveCorsOrigin => "Remove allowed CORS origin",
+        }
+    }
+
+    fn into_action(self, value: String) -> Action {
+        match self {
+            InputAction::AddCorsOrigin => Action::AddCorsOrigin(value),
+            InputAction::RemoveCorsOrigin => Action::RemoveCorsOrigin(value),
+        }
+    }
+}
+
+enum Mode {
+    Normal,
+    Filter,
+    Input {
+        action: InputAction,
+        value: String,
+    },
+    Confirm {
+        action: Action,
+        tenant: Box<Tenant>,
+    },
+    /// Confirm quitting while an operation is running, returns to the
+    /// `previous` mode when cancelled
+    ConfirmQuit {
+        previous: Box<Mode>,
+    },
+}
+
+/// Request made by the user from a key press
+enum Request {
+    Quit,
+    /// Quit even while an operation is running, abandoning the operation
+    ForceQuit,
+    LoadTenants,
+    LoadDetail(Tenant),
+    Run(Action, Tenant),
+}
+
+/// Outcome of a background task
+enum TaskOutcome {
+    Tenants(eyre::Result<Vec<Tenant>>),
+    Detail(eyre::Result<Box<TenantDetail>>),
+    Action(eyre::Result<String>),
+}
+
+type Task<'a> = Pin<Box<dyn Future<Output = TaskOutcome> + 'a>>;
+
+struct App {
+    tenants: Vec<Tenant>,
+    filter: String,
+    list_state: ListState,
+    /// Details and health checks of the selected tenant
+    detail: Option<TenantDetail>,
+    mode: Mode,
+    /// Message from the last task and whether it failed
+    status: Option<(String, bool)>,
+    /// Description of the running task
+    busy: Option<String>,
+}
+
+async fn run_app(
+    terminal: &mut DefaultTerminal,
+    ctx: TuiContext<'_>,
+    events: &mut mpsc::UnboundedReceiver<Event>,
+) -> eyre::Result<()> {
+    let mut app = App {
+        tenants: Vec::new(),
+        filter: String::new(),
+        list_state: ListState::default(),
+        detail: None,
+        mode: Mode::Normal,
+        status: None,
+        busy: Some("Loading tenants".to_string()),
+    };
+
+    let mut task: Option<Task<'_>> = Some(Box::pin(load_tenants(ctx)));
+
+    loop {
+        terminal
+            .draw(|frame| app.render(frame))
+            .context("failed to draw interface")?;
+
+        tokio::select! {
+            event = events.recv() => {
+                let Some(event) = event else {
+                    break;
+                };
+
+                let request = match event {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                    _ => None,
+                };
+
+                let next = match request {
+                    Some(Request::ForceQuit) => break,
+                    Some(_) if task.is_some() => {
+                        app.status = Some((
+                            "Wait for the running operation to finish, or press ctrl+c to abandon it and quit".to_string(),
+                            true,
+                        ));
+                        None
+                    }
+                    Some(Request::Quit) => break,
+                    Some(Request::LoadTenants) => {
+                        app.busy = Some("Loading tenants".to_string());
+                        Some(Box::pin(load_tenants(ctx)) as Task<'_>)
+                    }
+                    Some(Request::LoadDetail(tenant)) => {
+                        app.busy = Some(format!("Checking {}", tenant.name));
+                        Some(Box::pin(load_detail(ctx, tenant)) as Task<'_>)
+                    }
+                    Some(Request::Run(action, tenant)) => {
+                        app.busy = Some(action.describe(&tenant));
+                        Some(Box::pin(run_action(ctx, action, tenant)) as Task<'_>)
+                    }
+                    None => None,
+                };
+
+                if next.is_some() {
+                    task = next;
+                }
+            }
+            outcome = next_outcome(&mut task) => {
+                app.busy = None;
+                app.close_quit_confirm();
+                app.apply(outcome);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads terminal events on a separate thread, crossterm event reading
+/// is blocking
+struct EventReader {
+    events: mpsc::UnboundedReceiver<Event>,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl EventReader {
+    fn spawn() -> Self {
+        let (tx, events) = mpsc::unbounded_channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                // Poll with a timeout so the stop flag is checked between events
+                while !stop.load(Ordering::Relaxed) {
+                    match event::poll(EVENT_POLL_INTERVAL) {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(_) => break,
+                    }
+
+                    let Ok(event) = event::read() else {
+                        break;
+                    };
+
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            events,
+            stop,
+            thread,
+        }
+    }
+
+    /// Stop reading events and wait for the reader thread to exit
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if self.thread.join().is_err() {
+            tracing::error!("terminal event reader thread panicked");
+        }
+    }
+}
+
+/// Wait for the running task to complete, never completes when there
+/// is no running task
+async fn next_outcome(task: &mut Option<Task<'_>>) -> TaskOutcome {
+    let outcome = match task.as_mut() {
+        Some(task) => task.await,
+        None => std::future::pending().await,
+    };
+    *task = None;
+    outcome
+}
+
+async fn load_tenants(ctx: TuiContext<'_>) -> TaskOutcome {
+    let result = async {
+        let db_provider = ctx.backends.db_provider().await?;
+        let mut tenants = get_tenants(db_provider)
+            .await
+            .context("failed to load tenants")?;
+        tenants.sort_by(|a, b| (&a.name, &a.env).cmp(&(&b.name, &b.env)));
+        Ok(tenants)
+    }
+    .await;
+
+    TaskOutcome::Tenants(result)
+}
+
+async fn load_detail(ctx: TuiContext<'_>, tenant: Tenant) -> TaskOutcome {
+    let result = async {
+        let db_provider = ctx.backends.db_provider().await?;
+
+        let mut detail = get_tenant_detail(db_provider, tenant).await?;
+        detail.live = Some(
+            get_tenant_live_detail(
+                db_provider,
+                ctx.backends.secrets().await,
+                ctx.backends.storage_client().await,
+                ctx.backends.search_client().await,
+                ctx.backends.sqs_client().await,
+                &detail.tenant,
+            )
+            .await?,
+        );
+
+        Ok(detail)
+    }
+    .await;
+
+    TaskOutcome::Detail(result.map(Box::new))
+}
+
+async fn run_action(ctx: TuiContext<'_>, action: Action, tenant: Tenant) -> TaskOutcome {
+    let result = async {
+        match action {
+            Action::FlushCache => {
+                flush_tenant_cache(&ctx.config.api)
+                    .await
+                    .context("failed to flush tenant cache")?;
+
+                Ok("Flushed tenant cache".to_string())
+            }
+
+            Action::AddCorsOrigin(origin) => {
+                update_cors_origins(ctx, &tenant, CorsOriginsChange::Add(vec![origin])).await
+            }
+
+            Action::RemoveCorsOrigin(origin) => {
+                update_cors_origins(ctx, &tenant, CorsOriginsChange::Remove(vec![origin])).await
+            }
+
+            Action::RebuildIndex => {
+                let db_provider = ctx.backends.db_provider().await?;
+                let search = ctx.backends.search().await?;
+                let storage = ctx.backends.storage().await;
+
+                let search = search.create_search_index(&tenant);
+                let storage = storage.create_layer(tenant.storage_layer_options());
+
+                let db = db_provider
+                    .connect(&tenant.db_name)
+                    .await
+                    .context("failed to connect to tenant db")?;
+
+                let _guard = close_pool_on_drop(&db);
+
+                rebuild_tenant_index(&db, &search, &storage)
+                    .await
+                    .context("failed to rebuild tenant index")?;
+
+                Ok("Rebuilt search index".to_string())
+            }
+
+            Action::Migrate => {
+                let db_provider = ctx.backends.db_provider().await?;
+
+                let outcome = migrate_tenants(
+                    db_provider,
+                    MigrateTenantsConfig {
+                        env: Some(tenant.env.clone()),
+                        tenant_id: Some(tenant.id),
+                        skip_failed: false,
+                        target_migration_name: None,
+                    },
+                )
+                .await?;
+
+                if let Some((error, _)) = outcome.failed_tenants.first() {
+                    eyre::bail!("failed to migrate tenant: {error}");
+                }
+
+                Ok("Applied migrations".to_string())
+            }
+        }
+    }
+    .await;
+
+    TaskOutcome::Action(result)
+}
+
+async fn update_cors_origins(
+    ctx: TuiContext<'_>,
+    tenant: &Tenant,
+    change: CorsOriginsChange,
+) -> eyre::Result<String> {
+    let storage = ctx.backends.storage().await;
+    let storage_client = ctx.backends.storage_client().await;
+    let storage = storage.create_layer(tenant.storage_layer_options());
+    let diff = change
+        .apply_to_bucket(&storage, storage_client, false)
+        .await?;
+
+    if !diff.is_changed() {
+        return Ok("Allowed CORS origins unchanged".to_string());
+    }
+
+    Ok(format!(
+        "Allowed CORS origins are now: {}",
+        diff.current.join(", ")
+    ))
+}
+
+impl App {
+    /// Tenants matching the filter, matches any part of the name,
+    /// environment or ID
+    fn visible_tenants(&self) -> Vec<&Tenant> {
+        let filter = self.filter.to_lowercase();
+        self.tenants
+            .iter()
+            .filter(|tenant| {
+                filter.is_empty()
+                    || tenant.name.to_lowercase().contains(&filter)
+                    || tenant.env.to_lowercase().contains(&filter)
+                    || tenant.id.to_string().contains(&filter)
+            })
+            .collect()
+    }
+
+    fn selected_tenant(&self) -> Option<&Tenant> {
+        let index = self.list_state.selected()?;
+        self.visible_tenants().get(index).copied()
+    }
+
+    /// Detail of the selected tenant if it has been loaded
+    fn selected_detail(&self) -> Option<&TenantDetail> {
+        let tenant = self.selected_tenant()?;
+        self.detail
+            .as_ref()
+            .filter(|detail| detail.tenant.id == tenant.id && detail.tenant.env == tenant.env)
+    }
+
+    /// Keep the selection within the visible tenants
+    fn clamp_selection(&mut self) {
+        let count = self.visible_tenants().len();
+        let selected = match self.list_state.selected() {
+            _ if count == 0 => None,
+            Some(index) => Some(index.min(count - 1)),
+            None => Some(0),
+        };
+        self.list_state.select(selected);
+    }
+
+    fn apply(&mut self, outcome: TaskOutcome) {
+        match outcome {
+            TaskOutcome::Tenants(Ok(tenants)) => {
+                self.status = Some((format!("Loaded {} tenants", tenants.len()), false));
+                self.tenants = tenants;
+                self.clamp_selection();
+            }
+            TaskOutcome::Detail(Ok(detail)) => {
+                self.status = Some(("Health checks complete".to_string(), false));
+                self.detail = Some(*detail);
+            }
+            TaskOutcome::Action(Ok(message)) => {
+                self.status = Some((message, false));
+                // Details may no longer be accurate after the action
+                self.detail = None;
+            }
+            TaskOutcome::Tenants(Err(error))
+            | TaskOutcome::Detail(Err(error))
+            | TaskOutcome::Action(Err(error)) => {
+                self.status = Some((format!("{error:#}"), true));
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Request> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            // Abandoning a running operation can leave it partially applied
+            if self.busy.is_none() || matches!(self.mode, Mode::ConfirmQuit { .. }) {
+                return Some(Request::ForceQuit);
+            }
+
+            let previous = std::mem::replace(&mut self.mode, Mode::Normal);
+            self.mode = Mode::ConfirmQuit {
+                previous: Box::new(previous),
+            };
+            return None;
+        }
+
+        match &mut self.mode {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Filter => {
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Normal,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.mode = Mode::Normal;
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(char) => self.filter.push(char),
+                    _ => {}
+                }
+                self.clamp_selection();
+                None
+            }
+            Mode::Input { action, value } => {
+                match key.code {
+                    KeyCode::Enter if value.trim().is_empty() => {}
+                    KeyCode::Enter => {
+                        let action = action.into_action(value.trim().to_string());
+                        self.mode = match self.selected_tenant() {
+                            Some(tenant) => Mode::Confirm {
+                                action,
+                                tenant: Box::new(tenant.clone()),
+                            },
+                            None => Mode::Normal,
+                        };
+                    }
+                    KeyCode::Esc => self.mode = Mode::Normal,
+                    KeyCode::Backspace => {
+                        value.pop();
+                    }
+                    KeyCode::Char(char) => value.push(char),
+                    _ => {}
+                }
+                None
+            }
+            Mode::Confirm { action, tenant } => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    let request = Request::Run(action.clone(), tenant.as_ref().clone());
+                    self.mode = Mode::Normal;
+                    Some(request)
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    None
+                }
+                _ => None,
+            },
+            Mode::ConfirmQuit { .. } => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(Request::ForceQuit),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.close_quit_confirm();
+                    None
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Return to the previous mode if quitting is being confirmed
+    fn close_quit_confirm(&mut self) {
+        if let Mode::ConfirmQuit { previous } = &mut self.mode {
+            self.mode = std::mem::replace(previous.as_mut(), Mode::Normal);
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Option<Request> {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Request::Quit),
+            KeyCode::Down | KeyCode::Char('j') => self.list_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.list_state.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.list_state.select_last(),
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('r') => return Some(Request::LoadTenants),
+            KeyCode::Enter => return self.selected_tenant().cloned().map(Request::LoadDetail),
+            KeyCode::Char('f') => self.confirm(Action::FlushCache),
+            KeyCode::Char('i') => self.confirm(Action::RebuildIndex),
+            KeyCode::Char('m') => self.confirm(Action::Migrate),
+            KeyCode::Char('a') => self.input(InputAction::AddCorsOrigin),
+            KeyCode::Char('x') => self.input(InputAction::RemoveCorsOrigin),
+            _ => {}
+        }
+
+        self.clamp_selection();
+        None
+    }
+
+    fn confirm(&mut self, action: Action) {
+        if let Some(tenant) = self.selected_tenant() {
+            self.mode = Mode::Confirm {
+                action,
+                tenant: Box::new(tenant.clone()),
+            };
+        }
+    }
+
+    fn input(&mut self, action: InputAction) {
+        if self.selected_tenant().is_some() {
+            self.mode = Mode::Input {
+                action,
+                value: String::new(),
+            };
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [main_area, status_area, help_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main_area);
+
+        self.render_list(frame, list_area);
+        self.render_detail(frame, detail_area);
+        self.render_status(frame, status_area);
+        self.render_help(frame, help_area);
+
+        match &self.mode {
+            Mode::Input { action, value } => {
+                let paragraph = Paragraph::new(format!("{value}_"))
+                    .block(rounded_block().title(format!(" {} ", action.title())));
+                render_dialog(frame, paragraph, 3);
+            }
+            Mode::Confirm { action, tenant } => {
+                let paragraph = Paragraph::new(vec![
+                    Line::from(action.describe(tenant)),
+                    Line::from(""),
+                    Line::from("Press y to confirm or n to cancel").dim(),
+                ])
+                .wrap(Wrap { trim: true })
+                .block(rounded_block().title(" Confirm ").yellow());
+                render_dialog(frame, paragraph, 7);
+            }
+            Mode::ConfirmQuit { .. } => {
+                let running = self.busy.as_deref().unwrap_or_default();
+                let paragraph = Paragraph::new(vec![
+                    Line::from(format!("{running} is still running")),
+                    Line::from("Quitting now abandons it and may leave it partially applied"),
+                    Line::from(""),
+                    Line::from("Press y to quit or n to keep waiting").dim(),
+                ])
+                .wrap(Wrap { trim: true })
+                .block(rounded_block().title(" Quit ").red());
+                render_dialog(frame, paragraph, 8);
+            }
+            Mode::Normal | Mode::Filter => {}
+        }
+    }
+
+    fn render_list(&mut self, frame: &mut Frame, area: Rect) {
+        let tenants = self.visible_tenants();
+
+        let title = match (&self.mode, self.filter.is_empty()) {
+            (Mode::Filter, _) => format!(" Tenants /{}_ ", self.filter),
+            (_, false) => format!(" Tenants /{} ", self.filter),
+            (_, true) => " Tenants ".to_string(),
+        };
+
+        let items: Vec<ListItem> = tenants
+            .iter()
+            .map(|tenant| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(tenant.name.clone()),
+                    Span::raw(format!(" ({})", tenant.env)).dim(),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(rounded_block().title(title).title_bottom(format!(
+                " {}/{} ",
+                tenants.len(),
+                self.tenants.len()
+            )))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        frame.render_stateful_widget(list, area, &mut self.list_state);
+    }
+
+    fn render_detail(&self, frame: &mut Frame, area: Rect) {
+        let block = rounded_block().title(" Tenant ");
+
+        let Some(tenant) = self.selected_tenant() else {
+            frame.render_widget(Paragraph::new("No tenant selected").block(block), area);
+            return;
+        };
+
+        let (rows, block) = match self.selected_detail() {
+            Some(detail) => (detail.rows(), block),
+            None => (
+                tenant_rows(tenant),
+                block.title_bottom(" Press enter to run health checks "),
+            ),
+        };
+
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .map(|(label, value)| {
+                let height = value.lines().count().max(1) as u16;
+                let style = match value.as_str() {
+                    "Yes" => Style::new().fg(Color::Green),
+                    "No" => Style::new().fg(Color::Red),
+                    value if value.starts_with("Failed:") => Style::new().fg(Color::Red),
+                    _ => Style::new(),
+                };
+
+                Row::new(vec![
+                    Cell::from(label).bold(),
+                    Cell::from(value).style(style),
+                ])
+                .height(height)
+            })
+            .collect();
+
+        let table = Table::new(rows, [Constraint::Length(22), Constraint::Fill(1)]).block(block);
+        frame.render_widget(table, area);
+    }
+
+    fn render_status(&self, frame: &mut Frame, area: Rect) {
+        let line = match (&self.busy, &self.status) {
+            (Some(busy), _) => Line::from(format!("{busy}...")).yellow(),
+            (None, Some((message, true))) => Line::from(message.as_str()).red(),
+            (None, Some((message, false))) => Line::from(message.as_str()).green(),
+            (None, None) => Line::from(""),
+        };
+
+        frame.render_widget(line, area);
+    }
+
+    fn render_help(&self, frame: &mut Frame, area: Rect) {
+        let help = match self.mode {
+            Mode::Normal => {
+                "q quit  / filter  enter health checks  r reload  f flush cache  \
+                 a add origin  x remove origin  i rebuild index  m migrate"
+            }
+            Mode::Filter => "enter apply filter  esc clear filter",
+            Mode::Input { .. } => "enter continue  esc cancel",
+            Mode::Confirm { .. } => "y confirm  n cancel",
+            Mode::ConfirmQuit { .. } => "y quit  n keep waiting",
+        };
+
+        frame.render_widget(Line::from(help).dim(), area);
+    }
+}
+
+/// Bordered block matching the rounded tables of the human readable output
+fn rounded_block() -> Block<'static> {
+    Block::bordered().border_type(BorderType::Rounded)
+}
+
+/// Render a dialog of `height` lines centered over the interface
+fn render_dialog(frame: &mut Frame, widget: Paragraph, height: u16) {
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::horizontal([Constraint::Percentage(60)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+}